@@ -0,0 +1,76 @@
+use axum::body::Bytes;
+use lru::LruCache;
+use once_cell::sync::OnceCell;
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::must_new_cache_config;
+
+// 主题等更新后图表会变化，因此生成key时添加版本号
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+struct RenderCache {
+    ttl: Duration,
+    lru: Mutex<LruCache<String, (Instant, Bytes)>>,
+}
+
+/// 获取图表缓存，若配置的数量为0则不启用
+fn get_render_cache() -> Option<&'static RenderCache> {
+    static RENDER_CACHE: OnceCell<Option<RenderCache>> = OnceCell::new();
+    RENDER_CACHE
+        .get_or_init(|| {
+            let config = must_new_cache_config();
+            let size = NonZeroUsize::new(config.size as usize)?;
+            Some(RenderCache {
+                ttl: config.ttl,
+                lru: Mutex::new(LruCache::new(size)),
+            })
+        })
+        .as_ref()
+}
+
+/// 根据图表参数与输出格式生成缓存的key，
+/// 参数先转换为json value再序列化，因此字段的顺序与空白不影响结果，
+/// 缓存失效后相同参数生成的key也会变化
+pub fn new_render_key(value: &serde_json::Value, format: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(GENERATION.load(Ordering::Relaxed).to_be_bytes());
+    hasher.update(format.as_bytes());
+    hasher.update(b":");
+    hasher.update(value.to_string().as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// 从缓存中获取图表数据，已过期的则删除
+pub fn get_render_data(key: &str) -> Option<Bytes> {
+    let cache = get_render_cache()?;
+    let mut lru = cache.lru.lock().ok()?;
+    let (created_at, data) = lru.get(key)?;
+    if created_at.elapsed() < cache.ttl {
+        return Some(data.clone());
+    }
+    lru.pop(key);
+    None
+}
+
+/// 将图表数据写入缓存
+pub fn set_render_data(key: &str, data: Bytes) {
+    if let Some(cache) = get_render_cache() {
+        if let Ok(mut lru) = cache.lru.lock() {
+            lru.put(key.to_string(), (Instant::now(), data));
+        }
+    }
+}
+
+/// 使所有缓存失效，用于主题等影响图表生成的配置更新后
+pub fn invalidate_render_data() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    if let Some(cache) = get_render_cache() {
+        if let Ok(mut lru) = cache.lru.lock() {
+            lru.clear();
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+use crate::config::must_new_basic_config;
+use crate::error::{HttpError, HttpResult};
+
+// 压缩参数的编码方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Deflate,
+    Brotli,
+}
+
+impl Encoding {
+    /// 根据名称获取编码方式，不支持则返回None
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "deflate" => Some(Encoding::Deflate),
+            "br" | "brotli" => Some(Encoding::Brotli),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Deflate => "deflate",
+            Encoding::Brotli => "br",
+        }
+    }
+    /// 编码后数据的前缀，用于未指定编码方式时识别
+    fn prefix(&self) -> String {
+        format!("{}:", self.as_str())
+    }
+}
+
+fn new_codec_error(err: impl ToString) -> HttpError {
+    HttpError::new_with_category(&err.to_string(), "codec")
+}
+
+/// 压缩数据并以base64url(无padding)编码
+pub fn encode_compact(data: &[u8], encoding: Encoding) -> HttpResult<String> {
+    let buf = match encoding {
+        Encoding::Deflate => {
+            let mut encoder = DeflateEncoder::new(vec![], Compression::best());
+            encoder.write_all(data).map_err(new_codec_error)?;
+            encoder.finish().map_err(new_codec_error)?
+        }
+        Encoding::Brotli => {
+            let mut buf = vec![];
+            {
+                let mut encoder = brotli::CompressorWriter::new(&mut buf, 4096, 11, 22);
+                encoder.write_all(data).map_err(new_codec_error)?;
+            }
+            buf
+        }
+    };
+    Ok(URL_SAFE_NO_PAD.encode(buf))
+}
+
+/// 以前缀的形式编码，如`br:xxx`，解码时可根据前缀识别编码方式
+pub fn encode_compact_with_prefix(data: &[u8], encoding: Encoding) -> HttpResult<String> {
+    Ok(format!(
+        "{}{}",
+        encoding.prefix(),
+        encode_compact(data, encoding)?
+    ))
+}
+
+/// 解码base64url并解压，解压后的数据不能超过basic.maxBodySize(避免压缩炸弹)
+pub fn decode_compact(data: &str, encoding: Encoding) -> HttpResult<Vec<u8>> {
+    let invalid = || {
+        HttpError::new_with_category(
+            &format!("Data is not valid {} compact encoding", encoding.as_str()),
+            "codec",
+        )
+    };
+    let data = URL_SAFE_NO_PAD
+        .decode(data.trim_end_matches('='))
+        .map_err(|_| invalid())?;
+    let limit = must_new_basic_config().max_body_size as u64;
+    let reader: Box<dyn Read> = match encoding {
+        Encoding::Deflate => Box::new(DeflateDecoder::new(data.as_slice())),
+        Encoding::Brotli => Box::new(brotli::Decompressor::new(data.as_slice(), 4096)),
+    };
+    let mut buf = vec![];
+    reader
+        .take(limit + 1)
+        .read_to_end(&mut buf)
+        .map_err(|_| invalid())?;
+    if buf.len() as u64 > limit {
+        return Err(HttpError::new_with_category_status(
+            &format!("Decoded data exceeds the limit {limit} bytes"),
+            "body_too_large",
+            413,
+        ));
+    }
+    Ok(buf)
+}
+
+/// 解码参数，优先使用指定的编码方式，否则根据前缀识别，
+/// 均无则为原始数据
+pub fn decode_options(data: &str, encoding: Option<Encoding>) -> HttpResult<Vec<u8>> {
+    if let Some(encoding) = encoding {
+        let data = data.strip_prefix(&encoding.prefix()).unwrap_or(data);
+        return decode_compact(data, encoding);
+    }
+    for encoding in [Encoding::Deflate, Encoding::Brotli] {
+        if let Some(value) = data.strip_prefix(&encoding.prefix()) {
+            return decode_compact(value, encoding);
+        }
+    }
+    Ok(data.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_compact, decode_options, encode_compact, encode_compact_with_prefix, Encoding,
+    };
+    use crate::config::must_new_basic_config;
+
+    static DATA: &str = r#"{"type":"bar","series_list":[{"name":"Email","data":[120.0,132.0]}]}"#;
+
+    #[test]
+    fn encoding_from_name() {
+        assert_eq!(Some(Encoding::Deflate), Encoding::from_name("Deflate"));
+        assert_eq!(Some(Encoding::Brotli), Encoding::from_name("br"));
+        assert_eq!(Some(Encoding::Brotli), Encoding::from_name("BROTLI"));
+        assert_eq!(None, Encoding::from_name("gzip"));
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        for encoding in [Encoding::Deflate, Encoding::Brotli] {
+            let value = encode_compact(DATA.as_bytes(), encoding).unwrap();
+            assert!(!value.contains(['+', '/', '=']));
+            assert_eq!(DATA.as_bytes(), decode_compact(&value, encoding).unwrap());
+            // 指定编码方式时允许带前缀或不带前缀
+            assert_eq!(
+                DATA.as_bytes(),
+                decode_options(&value, Some(encoding)).unwrap()
+            );
+
+            let value = encode_compact_with_prefix(DATA.as_bytes(), encoding).unwrap();
+            assert!(value.starts_with(&format!("{}:", encoding.as_str())));
+            assert_eq!(DATA.as_bytes(), decode_options(&value, None).unwrap());
+            assert_eq!(
+                DATA.as_bytes(),
+                decode_options(&value, Some(encoding)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn decode_raw_options() {
+        assert_eq!(DATA.as_bytes(), decode_options(DATA, None).unwrap());
+    }
+
+    #[test]
+    fn decode_invalid_data() {
+        for encoding in [Encoding::Deflate, Encoding::Brotli] {
+            let err = decode_compact("!!!", encoding).unwrap_err();
+            assert_eq!(400, err.status);
+            assert_eq!("codec", err.category);
+
+            let value = encode_compact(DATA.as_bytes(), encoding).unwrap();
+            let err = decode_compact(&value[..value.len() / 2], encoding).unwrap_err();
+            assert_eq!("codec", err.category);
+        }
+    }
+
+    #[test]
+    fn decode_exceeds_limit() {
+        let limit = must_new_basic_config().max_body_size as usize;
+        let data = vec![b'0'; limit + 1];
+        for encoding in [Encoding::Deflate, Encoding::Brotli] {
+            let value = encode_compact(&data, encoding).unwrap();
+            // 压缩后的数据远小于限制，解压后超出
+            assert!(value.len() < limit);
+            let err = decode_compact(&value, encoding).unwrap_err();
+            assert_eq!(413, err.status);
+            assert_eq!("body_too_large", err.category);
+
+            let value = encode_compact(&data[..limit], encoding).unwrap();
+            assert_eq!(limit, decode_compact(&value, encoding).unwrap().len());
+        }
+    }
+}
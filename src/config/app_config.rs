@@ -1,7 +1,7 @@
 use config::{Config, File, FileFormat, FileSourceString};
 use once_cell::sync::OnceCell;
 use rust_embed::RustEmbed;
-use std::{collections::HashMap, env};
+use std::{collections::HashMap, env, time::Duration};
 use validator::Validate;
 
 #[derive(RustEmbed)]
@@ -15,6 +15,24 @@ fn convert_string_to_i32(value: String) -> i32 {
     0
 }
 
+/// 将字符串转换为Duration，支持ms,s,m,h的单位，
+/// 若无单位则为秒
+fn convert_string_to_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (num, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => (&value[..index], &value[index..]),
+        None => (value, "s"),
+    };
+    let num = num.parse::<u64>().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(num)),
+        "s" => Some(Duration::from_secs(num)),
+        "m" => Some(Duration::from_secs(num * 60)),
+        "h" => Some(Duration::from_secs(num * 3600)),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct APPConfig {
     // env变量的前缀
@@ -66,6 +84,32 @@ impl APPConfig {
         }
         default_value
     }
+    /// 从配置中获取对应的值(bool)，
+    /// 如果为空则使用默认值返回
+    fn get_bool_value_default(&self, key: &str, default_value: bool) -> bool {
+        match self.get_value(key).to_lowercase().as_str() {
+            "true" | "1" => true,
+            "false" | "0" => false,
+            _ => default_value,
+        }
+    }
+    /// 从配置中获取对应的值(Duration)，
+    /// 如果为空或格式不正确则使用默认值返回
+    fn get_duration_value_default(&self, key: &str, default_value: Duration) -> Duration {
+        convert_string_to_duration(&self.get_value(key)).unwrap_or(default_value)
+    }
+    /// 获取当前前缀下所有的配置项并转换为Duration，无法转换的忽略
+    fn get_duration_values(&self) -> HashMap<String, Duration> {
+        let mut values = HashMap::new();
+        if let Some(settings) = self.settings.get(&self.prefix) {
+            for (key, value) in settings.iter() {
+                if let Some(value) = convert_string_to_duration(value) {
+                    values.insert(key.clone(), value);
+                }
+            }
+        }
+        values
+    }
     /// 优先从env中获取配置的值，如果env中未配置则调用get_value获取
     fn get_value_from_env_first(&self, key: &str) -> String {
         let k = self.get_key(key);
@@ -117,14 +161,382 @@ pub struct BasicConfig {
     // 请求连接限制
     #[validate(range(min = 0, max = 100000))]
     pub request_limit: i32,
+    // 栅格化图片(png等)允许的最大像素数(宽x高，包括缩放)
+    #[validate(range(min = 1))]
+    pub max_pixels: i32,
+    // 请求数据的最大字节数
+    #[validate(range(min = 1))]
+    pub max_body_size: i32,
+    // 图表的最大宽度与高度
+    #[validate(range(min = 1))]
+    pub max_width: i32,
+    #[validate(range(min = 1))]
+    pub max_height: i32,
+    // 图表的最大series数量
+    #[validate(range(min = 1))]
+    pub max_series: i32,
+    // 每个series的最大数据点数量
+    #[validate(range(min = 1))]
+    pub max_points: i32,
+    // 批量生成与pdf报表最多的图表数量
+    #[validate(range(min = 1))]
+    pub max_batch_size: i32,
+}
+
+/// 获取基本配置，首次调用时加载并校验，后续直接使用
+pub fn must_new_basic_config() -> &'static BasicConfig {
+    static BASIC_CONFIG: OnceCell<BasicConfig> = OnceCell::new();
+    BASIC_CONFIG.get_or_init(|| {
+        let config = must_new_config().set_prefix("basic");
+        let basic_config = BasicConfig {
+            listen: config.get_value_from_env_first("listen"),
+            request_limit: config.get_int_value_default("requestLimit", 5000),
+            max_pixels: config.get_int_value_default("maxPixels", 4096 * 4096),
+            max_body_size: config.get_int_value_default("maxBodySize", 5 * 1024 * 1024),
+            max_width: config.get_int_value_default("maxWidth", 8192),
+            max_height: config.get_int_value_default("maxHeight", 8192),
+            max_series: config.get_int_value_default("maxSeries", 100),
+            max_points: config.get_int_value_default("maxPoints", 10000),
+            max_batch_size: config.get_int_value_default("maxBatchSize", 50),
+        };
+        basic_config.validate().unwrap();
+        basic_config
+    })
+}
+
+// 图片相关配置
+#[derive(Debug, Clone, Default, Validate)]
+pub struct ImageConfig {
+    // jpeg不支持透明，透明部分使用此颜色填充
+    #[validate(length(min = 4))]
+    pub jpeg_matte: String,
+}
+
+/// 获取图片配置，首次调用时加载并校验，后续直接使用
+pub fn must_new_image_config() -> &'static ImageConfig {
+    static IMAGE_CONFIG: OnceCell<ImageConfig> = OnceCell::new();
+    IMAGE_CONFIG.get_or_init(|| {
+        let config = must_new_config().set_prefix("image");
+        let mut jpeg_matte = config.get_value_from_env_first("jpegMatte");
+        if jpeg_matte.is_empty() {
+            jpeg_matte = "#FFFFFF".to_string();
+        }
+        let image_config = ImageConfig { jpeg_matte };
+        image_config.validate().unwrap();
+        image_config
+    })
+}
+
+// 图表缓存配置
+#[derive(Debug, Clone, Default, Validate)]
+pub struct CacheConfig {
+    // 缓存的图表数量，为0则不使用缓存
+    #[validate(range(min = 0, max = 100000))]
+    pub size: i32,
+    // 缓存有效期
+    pub ttl: Duration,
+}
+
+pub fn must_new_cache_config() -> CacheConfig {
+    let config = must_new_config().set_prefix("cache");
+    let cache_config = CacheConfig {
+        size: config.get_int_value("size"),
+        ttl: config.get_duration_value_default("ttl", Duration::from_secs(60)),
+    };
+    cache_config.validate().unwrap();
+    cache_config
+}
+
+// 图表配置
+#[derive(Debug, Clone, Default)]
+pub struct ChartsConfig {
+    // 严格模式下不支持的图表类型返回出错，而不是以bar生成
+    pub strict_type: bool,
+}
+
+/// 获取图表配置，首次调用时加载，后续直接使用
+pub fn must_new_charts_config() -> &'static ChartsConfig {
+    static CHARTS_CONFIG: OnceCell<ChartsConfig> = OnceCell::new();
+    CHARTS_CONFIG.get_or_init(|| {
+        let config = must_new_config().set_prefix("charts");
+        ChartsConfig {
+            strict_type: config.get_bool_value_default("strictType", true),
+        }
+    })
+}
+
+// 模板配置
+#[derive(Debug, Clone, Default)]
+pub struct TemplateConfig {
+    // 模板文件所在目录，为空则不加载
+    pub path: String,
+}
+
+pub fn must_new_template_config() -> TemplateConfig {
+    let config = must_new_config().set_prefix("template");
+    TemplateConfig {
+        path: config.get_value_from_env_first("path"),
+    }
+}
+
+// 主题配置
+#[derive(Debug, Clone, Default)]
+pub struct ThemeConfig {
+    // 主题文件所在目录，为空则不加载
+    pub path: String,
+}
+
+pub fn must_new_theme_config() -> ThemeConfig {
+    let config = must_new_config().set_prefix("theme");
+    ThemeConfig {
+        path: config.get_value_from_env_first("path"),
+    }
+}
+
+// 字体配置
+#[derive(Debug, Clone, Default, Validate)]
+pub struct FontConfig {
+    // 上传字体的保存目录，启动时也会从该目录加载字体，为空则不支持上传
+    pub path: String,
+    // 上传字体的最大字节数，中文字体较大因此单独限制
+    #[validate(range(min = 1))]
+    pub max_size: i32,
+}
+
+pub fn must_new_font_config() -> FontConfig {
+    let config = must_new_config().set_prefix("font");
+    let font_config = FontConfig {
+        path: config.get_value_from_env_first("path"),
+        max_size: config.get_int_value_default("maxSize", 64 * 1024 * 1024),
+    };
+    font_config.validate().unwrap();
+    font_config
+}
+
+// 图表生成线程池配置
+#[derive(Debug, Clone, Default, Validate)]
+pub struct RenderConfig {
+    // 线程数，为0则使用cpu核数
+    #[validate(range(min = 1, max = 1024))]
+    pub threads: usize,
+    // 等待队列的长度，队列满时请求返回503
+    #[validate(range(min = 1, max = 100000))]
+    pub queue_size: usize,
+}
+
+pub fn must_new_render_config() -> RenderConfig {
+    let config = must_new_config().set_prefix("render");
+    let mut threads = config.get_int_value("threads").max(0) as usize;
+    if threads == 0 {
+        threads = num_cpus::get();
+    }
+    let render_config = RenderConfig {
+        threads,
+        queue_size: config.get_int_value_default("queueSize", 128).max(0) as usize,
+    };
+    render_config.validate().unwrap();
+    render_config
+}
+
+// 图表生成的超时配置
+#[derive(Debug, Clone, Default)]
+pub struct TimeoutConfig {
+    // 未单独配置的输出格式使用的超时
+    pub default: Duration,
+    // 各输出格式的超时
+    pub formats: HashMap<String, Duration>,
+    // 各图表类型的超时
+    pub types: HashMap<String, Duration>,
+}
+
+impl TimeoutConfig {
+    /// 获取输出格式与图表类型对应的超时，
+    /// 若两者均有配置则使用较长的超时
+    pub fn get_timeout(&self, format: &str, chart_type: &str) -> Duration {
+        let timeout = *self.formats.get(format).unwrap_or(&self.default);
+        let chart_type = if chart_type.is_empty() {
+            "bar"
+        } else {
+            chart_type
+        };
+        match self.types.get(chart_type) {
+            Some(value) => timeout.max(*value),
+            None => timeout,
+        }
+    }
+    /// 所有配置中最长的超时
+    pub fn max_timeout(&self) -> Duration {
+        self.formats
+            .values()
+            .chain(self.types.values())
+            .fold(self.default, |a, b| a.max(*b))
+    }
+}
+
+/// 获取超时配置，首次调用时加载，后续直接使用
+pub fn must_new_timeout_config() -> &'static TimeoutConfig {
+    static TIMEOUT_CONFIG: OnceCell<TimeoutConfig> = OnceCell::new();
+    TIMEOUT_CONFIG.get_or_init(|| {
+        let config = must_new_config().set_prefix("timeout");
+        let mut formats = config.get_duration_values();
+        let default = formats.remove("default").unwrap_or(Duration::from_secs(30));
+        TimeoutConfig {
+            default,
+            formats,
+            types: must_new_config()
+                .set_prefix("typeTimeout")
+                .get_duration_values(),
+        }
+    })
+}
+
+// 图表分享配置
+#[derive(Debug, Clone, Default)]
+pub struct ShareConfig {
+    // 分享链接签名的密钥，为空则不支持分享
+    pub key: String,
+    // 分享链接的默认有效期，为0则不过期
+    pub ttl: Duration,
+    // 图表参数的保存目录，为空则压缩参数作为id
+    pub path: String,
+    // 分享链接的前缀
+    pub base_url: String,
+    // 是否允许未签名的预览
+    pub unsigned_preview: bool,
+}
+
+/// 获取分享配置，首次调用时加载，后续直接使用
+pub fn must_new_share_config() -> &'static ShareConfig {
+    static SHARE_CONFIG: OnceCell<ShareConfig> = OnceCell::new();
+    SHARE_CONFIG.get_or_init(|| {
+        let config = must_new_config().set_prefix("share");
+        ShareConfig {
+            key: config.get_value_from_env_first("key"),
+            ttl: config.get_duration_value_default("ttl", Duration::ZERO),
+            path: config.get_value_from_env_first("path"),
+            base_url: config
+                .get_value_from_env_first("baseUrl")
+                .trim_end_matches('/')
+                .to_string(),
+            unsigned_preview: config.get_bool_value_default("unsignedPreview", true),
+        }
+    })
+}
+
+// api key认证配置
+#[derive(Debug, Clone, Default, Validate)]
+pub struct AuthConfig {
+    // 是否启用认证
+    pub enabled: bool,
+    // api key列表，格式为id:key，以,分隔
+    pub keys: String,
+    // api key文件
+    pub file: String,
+    // 每个key每秒允许的请求数，为0则不限制
+    #[validate(range(min = 0))]
+    pub rate: i32,
+    // 每个key每天允许生成的图表数，为0则不限制
+    #[validate(range(min = 0))]
+    pub quota: i32,
+}
+
+pub fn must_new_auth_config() -> AuthConfig {
+    let config = must_new_config().set_prefix("auth");
+    let auth_config = AuthConfig {
+        enabled: config.get_bool_value_default("enabled", false),
+        keys: config.get_value_from_env_first("keys"),
+        file: config.get_value_from_env_first("file"),
+        rate: config.get_int_value("rate"),
+        quota: config.get_int_value("quota"),
+    };
+    auth_config.validate().unwrap();
+    auth_config
 }
 
-pub fn must_new_basic_config() -> BasicConfig {
-    let config = must_new_config().set_prefix("basic");
-    let basic_config = BasicConfig {
-        listen: config.get_value_from_env_first("listen"),
-        request_limit: config.get_int_value_default("requestLimit", 5000),
+// 客户端ip限流配置
+#[derive(Debug, Clone, Default, Validate)]
+pub struct RateLimitConfig {
+    // 每秒补充的令牌数，为0则不限制
+    #[validate(range(min = 0))]
+    pub rate: i32,
+    // 令牌桶的容量
+    #[validate(range(min = 0))]
+    pub burst: i32,
+    // 不限制的ip或网段
+    pub allowlist: Vec<String>,
+    // 各输出格式消耗的令牌数
+    pub weights: HashMap<String, i32>,
+    // 按画布大小加权的单位像素数，为0则不加权
+    #[validate(range(min = 0))]
+    pub pixels: i32,
+}
+
+pub fn must_new_rate_limit_config() -> RateLimitConfig {
+    let config = must_new_config().set_prefix("rateLimit");
+    let rate = config.get_int_value("rate");
+    let allowlist = config
+        .get_value_from_env_first("allowlist")
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect();
+    let weight_config = must_new_config().set_prefix("rateWeight");
+    let weights = ["svg", "png", "jpeg", "webp", "avif", "pdf"]
+        .iter()
+        .map(|format| {
+            let weight = weight_config.get_int_value_default(format, 1).max(1);
+            (format.to_string(), weight)
+        })
+        .collect();
+    let rate_limit_config = RateLimitConfig {
+        rate,
+        burst: config.get_int_value_default("burst", rate),
+        allowlist,
+        weights,
+        pixels: weight_config.get_int_value("pixels"),
+    };
+    rate_limit_config.validate().unwrap();
+    rate_limit_config
+}
+
+// 跨域配置
+#[derive(Debug, Clone, Default)]
+pub struct CorsConfig {
+    // 允许的来源，支持通配符，为空则不启用
+    pub origins: Vec<String>,
+    // 允许的请求方法
+    pub methods: Vec<String>,
+    // 允许的请求头
+    pub headers: Vec<String>,
+    // 允许浏览器获取的响应头
+    pub expose_headers: Vec<String>,
+    // 预检请求的缓存时长
+    pub max_age: Duration,
+    // 是否允许携带凭证
+    pub credentials: bool,
+}
+
+pub fn must_new_cors_config() -> CorsConfig {
+    let config = must_new_config().set_prefix("cors");
+    let get_values = |key: &str| -> Vec<String> {
+        config
+            .get_value_from_env_first(key)
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    };
+    let cors_config = CorsConfig {
+        origins: get_values("origins"),
+        methods: get_values("methods"),
+        headers: get_values("headers"),
+        expose_headers: get_values("exposeHeaders"),
+        max_age: config.get_duration_value_default("maxAge", Duration::from_secs(600)),
+        credentials: config.get_bool_value_default("credentials", false),
     };
-    basic_config.validate().unwrap();
-    basic_config
+    // 所有来源均可携带凭证访问是不安全的(与tower-http对Any的限制一致)
+    if cors_config.credentials && cors_config.origins.iter().any(|item| item == "*") {
+        panic!("Cors origins should not be * when credentials is true");
+    }
+    cors_config
 }
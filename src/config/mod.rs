@@ -1,3 +1,8 @@
 mod app_config;
 
-pub use app_config::{get_env, must_new_basic_config};
+pub use app_config::{
+    get_env, must_new_auth_config, must_new_basic_config, must_new_cache_config,
+    must_new_charts_config, must_new_cors_config, must_new_font_config, must_new_image_config,
+    must_new_rate_limit_config, must_new_render_config, must_new_share_config,
+    must_new_template_config, must_new_theme_config, must_new_timeout_config, BasicConfig,
+};
@@ -1,21 +1,47 @@
 use axum::body::{Body, Bytes};
-use axum::extract::Query;
-use axum::http::{header, HeaderValue, Request, Uri};
+use axum::extract::{Path, Query};
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode, Uri};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Json, Router};
-use http_body_util::BodyExt;
-use image::{load, ImageFormat};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use config::FileFormat;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::{load, ExtendedColorType, ImageEncoder, ImageFormat, Rgb, RgbImage};
 use rgb::RGBA8;
 use serde::Deserialize;
 use serde::Serialize;
 use snafu::{ResultExt, Snafu};
+use std::collections::HashMap;
 use std::io::Cursor;
+use std::time::{Duration, Instant};
 
+use crate::cache::{get_render_data, new_render_key, set_render_data};
+use crate::codec::{decode_options, encode_compact_with_prefix, Encoding};
+use crate::config::{
+    must_new_basic_config, must_new_charts_config, must_new_font_config, must_new_image_config,
+    must_new_share_config, must_new_timeout_config,
+};
 use crate::dist::{get_static_file, StaticFile};
 use crate::error::{HttpError, HttpResult};
+use crate::font::{list_fonts, save_font, FontInfo};
+use crate::guard::check_chart_limits;
+use crate::ingest::{csv_to_chart_params, get_chart_options, parse_chart_params, DataFormat};
+use crate::metrics::{encode_metrics, observe_png_quantize, observe_render};
+use crate::middleware::get_processing;
+use crate::pdf::svg_to_pdf;
+use crate::pool::{get_render_pool_stats, spawn_render};
+use crate::report::{render_report, ReportDocument, ReportItem};
+use crate::schema::{get_schema, validate, Problem, CHART_TYPES};
+use crate::share::{get_share, new_share, ShareResult};
+use crate::template::{add_template, expand_template, get_template, list_template_name};
+use crate::theme::{add_theme, get_theme, parse_theme};
+use crate::util::{get_header_value, scale_svg};
 use charts_rs::{
-    svg_to_avif, svg_to_png, svg_to_webp, BarChart, CandlestickChart, HeatmapChart,
+    svg_to_avif, svg_to_png, svg_to_webp, BarChart, CandlestickChart, Color, HeatmapChart,
     HorizontalBarChart, LineChart, MultiChart, PieChart, RadarChart, ScatterChart, TableChart,
 };
 
@@ -56,6 +82,75 @@ impl From<charts_rs::FontError> for HttpError {
     }
 }
 
+/// 将png转换为jpeg，由于jpeg不支持透明，
+/// 透明部分使用matte颜色填充
+fn png_to_jpeg(data: &[u8], matte: Color, quality: u8) -> Result<Vec<u8>, ImageError> {
+    let c = Cursor::new(data);
+    let rgba = load(c, ImageFormat::Png)
+        .context(ImageSnafu {
+            category: "load_image",
+        })?
+        .to_rgba8();
+    let mut rgb = RgbImage::new(rgba.width(), rgba.height());
+    for (src, dst) in rgba.pixels().zip(rgb.pixels_mut()) {
+        // resvg生成的像素为预乘alpha，因此只需要叠加背景色部分
+        let remain = 255 - src[3] as u32;
+        let blend = |value: u8, background: u8| -> u8 {
+            (value as u32 + (background as u32 * remain + 127) / 255).min(255) as u8
+        };
+        *dst = Rgb([
+            blend(src[0], matte.r),
+            blend(src[1], matte.g),
+            blend(src[2], matte.b),
+        ]);
+    }
+    let mut buf = vec![];
+    JpegEncoder::new_with_quality(&mut buf, quality)
+        .encode_image(&rgb)
+        .context(ImageSnafu {
+            category: "jpeg_encode",
+        })?;
+    Ok(buf)
+}
+
+/// 解析#rgb、#rgba、#rrggbb以及#rrggbbaa格式的颜色，
+/// charts-rs的Color::from不支持alpha，因此单独处理
+fn parse_hex_color(value: &str) -> Option<Color> {
+    let hex = value.trim().strip_prefix('#')?;
+    if !hex.is_ascii() {
+        return None;
+    }
+    let hex = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).collect::<String>(),
+        6 | 8 => hex.to_string(),
+        _ => return None,
+    };
+    let parse = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    Some(Color {
+        r: parse(0)?,
+        g: parse(2)?,
+        b: parse(4)?,
+        a: if hex.len() == 8 { parse(6)? } else { 255 },
+    })
+}
+
+/// 获取jpeg的填充色，图表的背景色由主题决定，
+/// 若主题的背景色不是完全不透明则使用配置的颜色(透明或无效时为白色)
+fn get_jpeg_matte(value: &serde_json::Value) -> Color {
+    let theme = value
+        .get("theme")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let background_color = charts_rs::get_theme(theme).background_color;
+    if background_color.is_nontransparent() {
+        return background_color;
+    }
+    match parse_hex_color(&must_new_image_config().jpeg_matte) {
+        Some(color) if color.is_nontransparent() => color,
+        _ => Color::white(),
+    }
+}
+
 // json响应的result
 pub type JsonResult<T> = HttpResult<Json<T>>;
 
@@ -64,44 +159,158 @@ pub fn new_router() -> Router {
         .route("/ping", get(ping))
         .route("/api/charts", get(preview))
         .route("/api/basic-info", get(get_basic_info))
+        .route("/api/stats", get(get_stats))
+        .route("/metrics", get(get_metrics))
         .route("/api/charts/svg", post(chart_svg))
         .route("/api/charts/png", post(chart_png))
         .route("/api/charts/webp", post(chart_webp))
         .route("/api/charts/avif", post(chart_avif))
         .route("/api/charts/jpeg", post(chart_jpeg))
+        .route("/api/charts/pdf", post(chart_pdf))
+        .route("/api/charts/render", post(chart_render))
+        .route("/api/charts/batch", post(chart_batch))
+        .route("/api/reports/pdf", post(report_pdf))
+        .route("/api/charts/share", post(chart_share))
+        .route("/api/charts/compact", post(chart_compact))
+        .route("/api/charts/validate", post(chart_validate))
+        .route("/api/schema/{type}", get(get_chart_schema))
+        .route("/api/fonts", get(list_chart_fonts).post(upload_chart_font))
+        .route(
+            "/api/themes/{name}",
+            get(get_chart_theme).put(put_chart_theme),
+        )
+        .route(
+            "/api/templates/{name}",
+            get(get_chart_template).put(put_chart_template),
+        )
+        .route("/c/{file}", get(shared_chart))
         .fallback(get(serve))
 }
 
 #[derive(Deserialize)]
 struct PrevieParams {
+    // 图表参数，可以是json或压缩编码的数据(如br:xxx)
     opts: String,
+    // 参数的编码方式，deflate或br，未指定则根据opts的前缀识别
+    enc: Option<String>,
     format: Option<String>,
+    scale: Option<f32>,
+    dpr: Option<f32>,
 }
 
-async fn preview(params: Query<PrevieParams>) -> HttpResult<Response> {
-    let format = match params
-        .format
-        .clone()
-        .unwrap_or_default()
-        .to_lowercase()
-        .as_str()
-    {
-        "png" => FormatType::Png,
-        "webp" => FormatType::Webp,
-        "avif" => FormatType::Avif,
-        "jpeg" => FormatType::Jpeg,
-        _ => FormatType::Svg,
-    };
-    render(params.opts.as_bytes(), format).await
+async fn preview(
+    method: Method,
+    headers: HeaderMap,
+    params: Query<PrevieParams>,
+) -> HttpResult<Response> {
+    if !must_new_share_config().unsigned_preview {
+        return Err(HttpError::new_with_category_status(
+            "Unsigned preview is disabled, please use share link",
+            "unsigned_preview",
+            403,
+        ));
+    }
+    let format = FormatType::from(params.format.clone().unwrap_or_default().as_str());
+    let encoding = params.enc.as_deref().map(get_encoding).transpose()?;
+    let opts = decode_options(&params.opts, encoding)?;
+    let mut value = parse_params(&opts, DataFormat::Json)?;
+    // query中的缩放比例优先
+    if let Some(scale) = params.scale.or(params.dpr) {
+        if let Some(map) = value.as_object_mut() {
+            map.insert("scale".to_string(), serde_json::json!(scale));
+        }
+    }
+    render(&headers, &method, value, format).await
+}
+
+/// 获取参数的编码方式，不支持则返回出错
+fn get_encoding(name: &str) -> HttpResult<Encoding> {
+    Encoding::from_name(name).ok_or_else(|| {
+        HttpError::new_with_category(
+            &format!("Encoding {name} is not supported, it should be deflate or br"),
+            "codec",
+        )
+    })
 }
 
-/// 读取http body
+#[derive(Deserialize)]
+struct CompactParams {
+    enc: Option<String>,
+    format: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CompactResult {
+    // 编码方式
+    enc: String,
+    // 编码后的参数(包括前缀)，可直接作为opts使用
+    opts: String,
+    // 原json的字节数
+    size: usize,
+    // 编码后的字节数
+    compact_size: usize,
+    // 预览的链接
+    url: String,
+}
+
+/// 将图表参数转换为压缩编码的形式，用于生成较短的预览链接，
+/// body支持json、yaml与toml，默认使用brotli
+async fn chart_compact(
+    params: Query<CompactParams>,
+    req: Request<Body>,
+) -> JsonResult<CompactResult> {
+    let encoding = get_encoding(params.enc.as_deref().unwrap_or("br"))?;
+    let headers = req.headers().clone();
+    let buf = read_http_body(req).await?;
+    // 不展开模板，保持参数最短
+    let data = parse_chart_params(&buf, DataFormat::from(&headers))?.to_string();
+    let opts = encode_compact_with_prefix(data.as_bytes(), encoding)?;
+    let mut url = format!("/api/charts?opts={opts}");
+    if let Some(format) = &params.format {
+        url = format!(
+            "{url}&format={}",
+            FormatType::from(format.as_str()).as_str()
+        );
+    }
+    Ok(Json(CompactResult {
+        enc: encoding.as_str().to_string(),
+        size: data.len(),
+        compact_size: opts.len(),
+        opts,
+        url,
+    }))
+}
+
+/// 读取http body，超出basic.maxBodySize则返回413
 async fn read_http_body(request: Request<Body>) -> HttpResult<Bytes> {
+    let limit = must_new_basic_config().max_body_size as usize;
+    read_http_body_with_limit(request, limit).await
+}
+
+/// 读取http body，content-length或实际读取的数据超出限制则返回413
+async fn read_http_body_with_limit(request: Request<Body>, limit: usize) -> HttpResult<Bytes> {
+    let body_too_large = || {
+        HttpError::new_with_category_status(
+            &format!("Request body exceeds the limit {limit} bytes"),
+            "body_too_large",
+            413,
+        )
+    };
+    let content_length = get_header_value(request.headers(), header::CONTENT_LENGTH.as_str());
+    if content_length.parse::<usize>().unwrap_or_default() > limit {
+        return Err(body_too_large());
+    }
     let (_, body) = request.into_parts();
-    let bytes = body
+    let bytes = Limited::new(body, limit)
         .collect()
         .await
-        .map_err(|err| HttpError::new_with_category(&err.to_string(), "body_to_bytes"))?
+        .map_err(|err| {
+            if err.is::<LengthLimitError>() {
+                body_too_large()
+            } else {
+                HttpError::new_with_category(&err.to_string(), "body_to_bytes")
+            }
+        })?
         .to_bytes();
     Ok(bytes)
 }
@@ -119,10 +328,20 @@ async fn serve(uri: Uri) -> StaticFile {
     get_static_file(filename)
 }
 
+async fn get_metrics() -> Response {
+    let render_stats = get_render_pool_stats();
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        encode_metrics(get_processing(), render_stats.queued, render_stats.running),
+    )
+        .into_response()
+}
+
 #[derive(Debug, Clone, Serialize, Default)]
 struct BasicInfoResult {
     pub families: Vec<String>,
     pub themes: Vec<String>,
+    pub templates: Vec<String>,
     pub version: String,
 }
 
@@ -131,38 +350,305 @@ async fn get_basic_info() -> JsonResult<BasicInfoResult> {
     Ok(Json(BasicInfoResult {
         families,
         themes: charts_rs::list_theme_name(),
+        templates: list_template_name(),
         version: charts_rs::version(),
     }))
 }
 
+#[derive(Debug, Clone, Serialize, Default)]
+struct StatsResult {
+    // 当前处理中的请求数
+    pub processing: usize,
+    // 允许同时处理的请求数
+    pub request_limit: i32,
+    // 图表生成线程池的线程数与队列长度
+    pub render_threads: usize,
+    pub render_queue_size: usize,
+    // 线程池中等待与正在生成的图表数
+    pub render_queued: usize,
+    pub render_running: usize,
+}
+
+async fn get_stats() -> JsonResult<StatsResult> {
+    let render_stats = get_render_pool_stats();
+    Ok(Json(StatsResult {
+        processing: get_processing(),
+        request_limit: must_new_basic_config().request_limit,
+        render_threads: render_stats.threads,
+        render_queue_size: render_stats.queue_size,
+        render_queued: render_stats.queued,
+        render_running: render_stats.running,
+    }))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum FormatType {
     Svg,
     Png,
     Webp,
     Avif,
     Jpeg,
+    Pdf,
+}
+
+impl From<&str> for FormatType {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "png" => FormatType::Png,
+            "webp" => FormatType::Webp,
+            "avif" => FormatType::Avif,
+            "jpeg" => FormatType::Jpeg,
+            "pdf" => FormatType::Pdf,
+            _ => FormatType::Svg,
+        }
+    }
+}
+
+impl FormatType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FormatType::Svg => "svg",
+            FormatType::Png => "png",
+            FormatType::Webp => "webp",
+            FormatType::Avif => "avif",
+            FormatType::Jpeg => "jpeg",
+            FormatType::Pdf => "pdf",
+        }
+    }
+    fn content_type(&self) -> HeaderValue {
+        match self {
+            FormatType::Png => HeaderValue::from_static(mime::IMAGE_PNG.as_ref()),
+            FormatType::Avif => HeaderValue::from_static("image/avif"),
+            FormatType::Webp => HeaderValue::from_static("image/webp"),
+            FormatType::Jpeg => HeaderValue::from_static(mime::IMAGE_JPEG.as_ref()),
+            FormatType::Svg => HeaderValue::from_static(mime::IMAGE_SVG.as_ref()),
+            FormatType::Pdf => HeaderValue::from_static(mime::APPLICATION_PDF.as_ref()),
+        }
+    }
+}
+
+// 支持的输出格式，accept为通配符时按此顺序选择
+static FORMAT_TYPES: [FormatType; 6] = [
+    FormatType::Svg,
+    FormatType::Png,
+    FormatType::Webp,
+    FormatType::Avif,
+    FormatType::Jpeg,
+    FormatType::Pdf,
+];
+
+/// 根据accept选择输出格式，q值高的优先，q值相同则按accept中的顺序，
+/// 通配符(*/*与image/*)则选择第一个支持且未被q=0排除的格式
+fn negotiate_format(accept: &str) -> Option<FormatType> {
+    let accept = if accept.trim().is_empty() {
+        "*/*"
+    } else {
+        accept
+    };
+    let mut items = vec![];
+    for item in accept.split(',') {
+        let mut arr = item.split(';');
+        let media = arr.next().unwrap_or_default().trim().to_lowercase();
+        if media.is_empty() {
+            continue;
+        }
+        let q = arr
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|value| value.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        items.push((media, q));
+    }
+    // 排序为稳定排序，q值相同保持accept中的顺序
+    items.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let is_excluded = |format: &FormatType| {
+        items.iter().any(|(media, q)| {
+            *q <= 0.0 && media == format.content_type().to_str().unwrap_or_default()
+        })
+    };
+    for (media, q) in items.iter() {
+        if *q <= 0.0 {
+            break;
+        }
+        let found = FORMAT_TYPES.iter().find(|format| {
+            let content_type = format.content_type();
+            let content_type = content_type.to_str().unwrap_or_default();
+            let matched = if media == "*/*" {
+                true
+            } else if let Some(prefix) = media.strip_suffix("/*") {
+                content_type.starts_with(&format!("{prefix}/"))
+            } else {
+                media == content_type
+            };
+            matched && !is_excluded(format)
+        });
+        if let Some(format) = found {
+            return Some(*format);
+        }
+    }
+    None
+}
+
+/// 根据accept选择输出格式的名称，用于限流时按格式计算消耗
+pub fn negotiate_format_name(accept: &str) -> Option<&'static str> {
+    negotiate_format(accept).map(|format| format.as_str())
+}
+
+/// 根据accept选择输出格式生成图表，响应设置Vary: Accept，
+/// 无匹配的格式则返回406
+async fn chart_render(req: Request<Body>) -> Response {
+    let accept = get_header_value(req.headers(), header::ACCEPT.as_str());
+    let mut resp = if let Some(format) = negotiate_format(&accept) {
+        render_from_bdoy(req, format).await.into_response()
+    } else {
+        HttpError {
+            status: 406,
+            extra: Some(
+                FORMAT_TYPES
+                    .iter()
+                    .map(|format| {
+                        format
+                            .content_type()
+                            .to_str()
+                            .unwrap_or_default()
+                            .to_string()
+                    })
+                    .collect(),
+            ),
+            ..HttpError::new_with_category(
+                &format!("Accept {accept} is not supported"),
+                "not_acceptable",
+            )
+        }
+        .into_response()
+    };
+    resp.headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("accept"));
+    resp
 }
 
 async fn render_from_bdoy(req: Request<Body>, format: FormatType) -> HttpResult<Response> {
-    // TODO 是否校验content-type
+    let headers = req.headers().clone();
+    let method = req.method().clone();
+    let value = read_chart_params(req).await?;
+    render(&headers, &method, value, format).await
+}
+
+/// 根据content-type解析body，支持json、yaml、toml、csv以及tsv，
+/// csv与tsv的图表参数通过query或header指定
+async fn read_chart_params(req: Request<Body>) -> HttpResult<serde_json::Value> {
+    let headers = req.headers().clone();
+    let query = Query::<HashMap<String, String>>::try_from_uri(req.uri())
+        .map(|query| query.0)
+        .unwrap_or_default();
     let buf = read_http_body(req).await?;
-    render(buf.as_ref(), format).await
+    let value = match DataFormat::from(&headers) {
+        DataFormat::Csv => expand_template(csv_to_chart_params(
+            &buf,
+            b',',
+            get_chart_options(&headers, &query)?,
+        )?)?,
+        DataFormat::Tsv => expand_template(csv_to_chart_params(
+            &buf,
+            b'\t',
+            get_chart_options(&headers, &query)?,
+        )?)?,
+        data_format => parse_params(&buf, data_format)?,
+    };
+    Ok(value)
 }
 
-async fn render(params: &[u8], format: FormatType) -> HttpResult<Response> {
-    let json = std::string::String::from_utf8_lossy(params);
-    let value: serde_json::Value = serde_json::from_str(&json)?;
-    let chart_type = if let Some(value) = value.get("type") {
-        value.as_str().unwrap_or_default()
+/// 解析图表参数，若指定了模板则与模板合并
+fn parse_params(params: &[u8], format: DataFormat) -> HttpResult<serde_json::Value> {
+    let value = parse_chart_params(params, format)?;
+    expand_template(value)
+}
+
+/// 是否返回304，仅GET与HEAD的请求支持(RFC 9110 13.1.2)，
+/// etag使用弱比较，*则仅在图表已生成(缓存中)时匹配
+fn is_not_modified(method: &Method, if_none_match: &str, entity_tag: &str, exists: bool) -> bool {
+    if method != Method::GET && method != Method::HEAD {
+        return false;
+    }
+    if_none_match.split(',').any(|item| {
+        let item = item.trim();
+        (item == "*" && exists) || item.trim_start_matches("W/") == entity_tag
+    })
+}
+
+async fn render(
+    headers: &HeaderMap,
+    method: &Method,
+    value: serde_json::Value,
+    format: FormatType,
+) -> HttpResult<Response> {
+    let key = new_render_key(&value, format.as_str());
+    // 相同的参数与格式生成的图表一致，因此以key作为etag
+    let entity_tag = format!(r#""{key}""#);
+    let if_none_match = get_header_value(headers, header::IF_NONE_MATCH.as_str());
+    let cached_data = get_render_data(&key);
+    if is_not_modified(method, &if_none_match, &entity_tag, cached_data.is_some()) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, entity_tag)]).into_response());
+    }
+
+    let data = if let Some(data) = cached_data {
+        data
     } else {
-        ""
+        check_render_limits(&value, format)?;
+        let timeout = get_render_timeout(&value, format.as_str());
+        let data = spawn_render(format.as_str(), timeout, move || {
+            render_data(&value, format)
+        })
+        .await?;
+        set_render_data(&key, data.clone());
+        data
     };
-    let mut quality: u8 = 80;
-    if let Some(value) = value.get("quality") {
-        let v = value.as_u64().unwrap_or_default();
-        if v < 100 {
-            quality = v as u8;
+    Ok((
+        [(header::CONTENT_TYPE, format.content_type())],
+        [(header::ETAG, entity_tag)],
+        data,
+    )
+        .into_response())
+}
+
+/// 校验图表类型(包括子图表)是否支持，未指定类型则为bar
+fn check_chart_type(value: &serde_json::Value) -> HttpResult<()> {
+    let mut values = vec![value];
+    if let Some(child_charts) = value.get("child_charts").and_then(|v| v.as_array()) {
+        values.extend(child_charts.iter());
+    }
+    for item in values {
+        let Some(chart_type) = item.get("type") else {
+            continue;
+        };
+        if let Some(chart_type) = chart_type.as_str() {
+            if chart_type.is_empty() || CHART_TYPES.contains(&chart_type) {
+                continue;
+            }
         }
+        return Err(HttpError {
+            message: format!("Chart type {chart_type} is not supported"),
+            category: "unsupported_chart_type".to_string(),
+            extra: Some(CHART_TYPES.iter().map(|item| item.to_string()).collect()),
+            ..Default::default()
+        });
+    }
+    Ok(())
+}
+
+/// 是否为严格模式，优先使用请求参数的配置
+fn is_strict_type(value: &serde_json::Value) -> bool {
+    value
+        .get("strict")
+        .and_then(|v| v.as_bool())
+        .unwrap_or_else(|| must_new_charts_config().strict_type)
+}
+
+/// 根据参数生成svg图表
+fn render_svg(value: &serde_json::Value) -> HttpResult<String> {
+    let json = value.to_string();
+    let chart_type = get_chart_type(value);
+    if is_strict_type(value) {
+        check_chart_type(value)?;
     }
 
     let svg = match chart_type {
@@ -207,82 +693,283 @@ async fn render(params: &[u8], format: FormatType) -> HttpResult<Response> {
             chart.svg()?
         }
     };
+    Ok(svg)
+}
+
+/// 获取栅格化的缩放比例(scale或dpr)，默认为1
+fn get_scale(value: &serde_json::Value) -> HttpResult<f32> {
+    let Some(scale) = value.get("scale").or_else(|| value.get("dpr")) else {
+        return Ok(1.0);
+    };
+    match scale.as_f64() {
+        Some(scale) if scale > 0.0 && scale.is_finite() => Ok(scale as f32),
+        _ => Err(HttpError::new_with_category(
+            &format!("Scale {scale} is invalid, it should be greater than 0"),
+            "scale",
+        )),
+    }
+}
+
+/// 在线程池生成图表前校验参数是否超出限制，
+/// 栅格化的格式按缩放后的像素数校验
+fn check_render_limits(value: &serde_json::Value, format: FormatType) -> HttpResult<()> {
+    let scale = if matches!(format, FormatType::Svg | FormatType::Pdf) {
+        1.0
+    } else {
+        get_scale(value)?
+    };
+    check_chart_limits(value, scale, must_new_basic_config())
+}
+
+/// 按比例调整栅格化的分辨率
+fn scale_raster_svg(svg: String, scale: f32) -> String {
+    if scale == 1.0 {
+        return svg;
+    }
+    scale_svg(&svg, scale).unwrap_or(svg)
+}
+
+/// 获取图表生成的超时，按输出格式与图表类型配置
+fn get_render_timeout(value: &serde_json::Value, format: &str) -> Duration {
+    must_new_timeout_config().get_timeout(format, get_chart_type(value))
+}
+
+fn get_chart_type(value: &serde_json::Value) -> &str {
+    if let Some(value) = value.get("type") {
+        value.as_str().unwrap_or_default()
+    } else {
+        ""
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PngOptions {
+    // 压缩后的最大颜色数(2-256)
+    max_colors: Option<u32>,
+    // 抖动等级(0-1)，默认为1
+    dithering: Option<f32>,
+    // 压缩速度(1-10)，越慢压缩效果越好
+    speed: Option<i32>,
+    // zlib压缩等级(0-9)
+    compression_level: Option<u8>,
+    // 是否去除元数据，默认为true，若为false则写入软件名称与标题
+    strip_metadata: Option<bool>,
+}
+
+impl PngOptions {
+    fn is_default(&self) -> bool {
+        *self == PngOptions::default()
+    }
+}
+
+/// 获取png的压缩参数，并校验各参数的范围
+fn get_png_options(value: &serde_json::Value) -> HttpResult<PngOptions> {
+    let Some(png) = value.get("png") else {
+        return Ok(PngOptions::default());
+    };
+    let options: PngOptions = serde_json::from_value(png.clone())
+        .map_err(|err| HttpError::new_with_category(&err.to_string(), "png"))?;
+    let invalid = |message: &str| Err(HttpError::new_with_category(message, "png"));
+    if options.max_colors.is_some_and(|v| !(2..=256).contains(&v)) {
+        return invalid("png.max_colors should be between 2 and 256");
+    }
+    if options.dithering.is_some_and(|v| !(0.0..=1.0).contains(&v)) {
+        return invalid("png.dithering should be between 0 and 1");
+    }
+    if options.speed.is_some_and(|v| !(1..=10).contains(&v)) {
+        return invalid("png.speed should be between 1 and 10");
+    }
+    if options.compression_level.is_some_and(|v| v > 9) {
+        return invalid("png.compression_level should be between 0 and 9");
+    }
+    Ok(options)
+}
+
+/// 请求中指定的质量(1-100)，用于webp与avif
+fn get_explicit_quality(value: &serde_json::Value) -> Option<u8> {
+    value
+        .get("quality")
+        .and_then(|v| v.as_u64())
+        .filter(|v| (1..=100).contains(v))
+        .map(|v| v as u8)
+}
+
+/// 解码png为rgba数据
+fn decode_png(data: &[u8]) -> HttpResult<(Vec<u8>, u32, u32)> {
+    let c = Cursor::new(data);
+    let dynamic_image = load(c, ImageFormat::Png).context(ImageSnafu {
+        category: "load_image",
+    })?;
+    let width = dynamic_image.width();
+    let height = dynamic_image.height();
+    Ok((dynamic_image.to_rgba8().into_raw(), width, height))
+}
+
+/// 重新编码png，quality不为0时使用imagequant压缩颜色，
+/// 其它参数(颜色数、抖动、速度、压缩等级与元数据)由png参数指定
+fn encode_png(
+    data: &[u8],
+    quality: u8,
+    options: &PngOptions,
+    value: &serde_json::Value,
+) -> HttpResult<Vec<u8>> {
+    let (rgba, width, height) = decode_png(data)?;
+    let (width, height) = (width as usize, height as usize);
+    let buffer: Vec<RGBA8> = rgba
+        .chunks(4)
+        .map(|ele| RGBA8 {
+            r: ele[0],
+            g: ele[1],
+            b: ele[2],
+            a: ele[3],
+        })
+        .collect();
+
+    let mut enc = lodepng::Encoder::new();
+    if let Some(level) = options.compression_level {
+        enc.settings_mut().zlibsettings.set_level(level);
+    }
+    if options.strip_metadata == Some(false) {
+        enc.info_png_mut()
+            .add_text("Software", "charts-rs-web")
+            .context(LodePNGSnafu {
+                category: "png_text",
+            })?;
+        if let Some(title) = value.get("title_text").and_then(|v| v.as_str()) {
+            enc.info_png_mut()
+                .add_text("Title", title)
+                .context(LodePNGSnafu {
+                    category: "png_text",
+                })?;
+        }
+    }
+    if quality == 0 {
+        let buf = enc.encode(&buffer, width, height).context(LodePNGSnafu {
+            category: "png_encode",
+        })?;
+        return Ok(buf);
+    }
+
+    let mut liq = imagequant::new();
+    liq.set_quality(0, quality).context(ImageQuantSnafu {
+        category: "png_set_quality",
+    })?;
+    if let Some(max_colors) = options.max_colors {
+        liq.set_max_colors(max_colors).context(ImageQuantSnafu {
+            category: "png_set_max_colors",
+        })?;
+    }
+    if let Some(speed) = options.speed {
+        liq.set_speed(speed).context(ImageQuantSnafu {
+            category: "png_set_speed",
+        })?;
+    }
+
+    let mut img = liq
+        .new_image(buffer, width, height, 0.0)
+        .context(ImageQuantSnafu {
+            category: "png_new_image",
+        })?;
+
+    let mut res = liq.quantize(&mut img).context(ImageQuantSnafu {
+        category: "png_quantize",
+    })?;
+
+    res.set_dithering_level(options.dithering.unwrap_or(1.0))
+        .context(ImageQuantSnafu {
+            category: "png_set_level",
+        })?;
+
+    let (palette, pixels) = res.remapped(&mut img).context(ImageQuantSnafu {
+        category: "png_remapped",
+    })?;
+    enc.set_palette(&palette).context(LodePNGSnafu {
+        category: "png_encoder",
+    })?;
+
+    let buf = enc.encode(&pixels, width, height).context(LodePNGSnafu {
+        category: "png_encode",
+    })?;
+    Ok(buf)
+}
+
+/// 根据参数生成对应格式的图表数据
+fn render_data(value: &serde_json::Value, format: FormatType) -> HttpResult<Bytes> {
+    let start_at = Instant::now();
+    let chart_type = get_chart_type(value);
+    let quality = match value.get("quality").and_then(|v| v.as_u64()) {
+        // png的质量为0时表示不压缩
+        Some(0) => 0,
+        Some(v) if (1..=100).contains(&v) => v as u8,
+        _ => 80,
+    };
+    let scale = get_scale(value)?;
+    let mut svg = render_svg(value)?;
+    if !matches!(format, FormatType::Svg | FormatType::Pdf) {
+        svg = scale_raster_svg(svg, scale);
+    }
 
     let data = match format {
         FormatType::Svg => Bytes::from(svg),
+        FormatType::Pdf => Bytes::from(svg_to_pdf(&svg)?),
         FormatType::Webp => {
-            let data = svg_to_webp(&svg)?;
+            // 指定了质量则为有损压缩，否则为无损
+            let data = if let Some(quality) = get_explicit_quality(value) {
+                let (rgba, width, height) = decode_png(&svg_to_png(&svg)?)?;
+                webp::Encoder::from_rgba(&rgba, width, height)
+                    .encode(quality as f32)
+                    .to_vec()
+            } else {
+                svg_to_webp(&svg)?
+            };
             Bytes::from(data)
         }
         FormatType::Avif => {
-            let data = svg_to_avif(&svg)?;
+            let data = if let Some(quality) = get_explicit_quality(value) {
+                let (rgba, width, height) = decode_png(&svg_to_png(&svg)?)?;
+                let mut buf = vec![];
+                AvifEncoder::new_with_speed_quality(&mut buf, 4, quality)
+                    .write_image(&rgba, width, height, ExtendedColorType::Rgba8)
+                    .context(ImageSnafu {
+                        category: "avif_encode",
+                    })?;
+                buf
+            } else {
+                svg_to_avif(&svg)?
+            };
             Bytes::from(data)
         }
-        _ => {
+        FormatType::Jpeg => {
+            let data = svg_to_png(&svg)?;
+            // 未设置或不在1-100范围内的质量使用默认值
+            let quality = get_explicit_quality(value).unwrap_or(80);
+            let buf = png_to_jpeg(&data, get_jpeg_matte(value), quality)?;
+            Bytes::from(buf)
+        }
+        FormatType::Png => {
             let data = svg_to_png(&svg)?;
-            if quality == 0 {
+            let png_options = get_png_options(value)?;
+            if quality == 0 && png_options.is_default() {
                 Bytes::from(data)
             } else {
-                let mut liq = imagequant::new();
-                liq.set_quality(0, quality).context(ImageQuantSnafu {
-                    category: "png_set_quality",
-                })?;
-
-                let c = Cursor::new(&data);
-                let dynamic_image = load(c, ImageFormat::Png).context(ImageSnafu {
-                    category: "load_image",
-                })?;
-                let width = dynamic_image.width() as usize;
-                let height = dynamic_image.height() as usize;
-                let mut buffer = Vec::with_capacity(width * height);
-
-                for ele in dynamic_image.to_rgba8().chunks(4) {
-                    buffer.push(RGBA8 {
-                        r: ele[0],
-                        g: ele[1],
-                        b: ele[2],
-                        a: ele[3],
-                    })
+                let quantize_start_at = Instant::now();
+                let buf = encode_png(&data, quality, &png_options, value)?;
+                if quality != 0 {
+                    observe_png_quantize(quantize_start_at.elapsed());
                 }
-
-                let mut img =
-                    liq.new_image(buffer, width, height, 0.0)
-                        .context(ImageQuantSnafu {
-                            category: "png_new_image",
-                        })?;
-
-                let mut res = liq.quantize(&mut img).context(ImageQuantSnafu {
-                    category: "png_quantize",
-                })?;
-
-                res.set_dithering_level(1.0).context(ImageQuantSnafu {
-                    category: "png_set_level",
-                })?;
-
-                let (palette, pixels) = res.remapped(&mut img).context(ImageQuantSnafu {
-                    category: "png_remapped",
-                })?;
-                let mut enc = lodepng::Encoder::new();
-                enc.set_palette(&palette).context(LodePNGSnafu {
-                    category: "png_encoder",
-                })?;
-
-                let buf = enc.encode(&pixels, width, height).context(LodePNGSnafu {
-                    category: "png_encode",
-                })?;
-
                 Bytes::from(buf)
             }
         }
     };
-    let content_type = match format {
-        FormatType::Png => HeaderValue::from_static(mime::IMAGE_PNG.as_ref()),
-        FormatType::Avif => HeaderValue::from_static("image/avif"),
-        FormatType::Webp => HeaderValue::from_static("image/webp"),
-        FormatType::Jpeg => HeaderValue::from_static(mime::JPEG.as_ref()),
-        _ => HeaderValue::from_static(mime::IMAGE_SVG.as_ref()),
+    // 未支持的类型以bar生成，避免label数量不可控
+    let type_label = if CHART_TYPES.contains(&chart_type) {
+        chart_type
+    } else {
+        "bar"
     };
-    Ok(([(header::CONTENT_TYPE, content_type)], data).into_response())
+    observe_render(type_label, format.as_str(), start_at.elapsed(), data.len());
+    Ok(data)
 }
 
 async fn chart_svg(req: Request<Body>) -> HttpResult<Response> {
@@ -304,3 +991,563 @@ async fn chart_avif(req: Request<Body>) -> HttpResult<Response> {
 async fn chart_jpeg(req: Request<Body>) -> HttpResult<Response> {
     render_from_bdoy(req, FormatType::Jpeg).await
 }
+
+async fn chart_pdf(req: Request<Body>) -> HttpResult<Response> {
+    render_from_bdoy(req, FormatType::Pdf).await
+}
+
+/// 生成多页的pdf报表，报表中的图表参数同样支持模板
+async fn report_pdf(req: Request<Body>) -> HttpResult<Response> {
+    let start_at = Instant::now();
+    let data_format = DataFormat::from(req.headers());
+    let buf = read_http_body(req).await?;
+    let mut doc: ReportDocument = serde_json::from_value(parse_chart_params(&buf, data_format)?)?;
+    doc.check(must_new_basic_config().max_batch_size as usize)?;
+    // 展开模板并校验图表参数后再提交至线程池
+    for (index, item) in doc.items.iter_mut().enumerate() {
+        if let ReportItem::Chart { options } = item {
+            *options = expand_template(options.take())?;
+            check_render_limits(options, FormatType::Pdf).map_err(|mut err| {
+                err.message = format!("Render item {index} fail: {}", err.message);
+                err
+            })?;
+        }
+    }
+    let timeout = must_new_timeout_config().get_timeout("report", "");
+    let data = spawn_render("report", timeout, move || render_report(&doc, render_svg)).await?;
+    observe_render(
+        "report",
+        FormatType::Pdf.as_str(),
+        start_at.elapsed(),
+        data.len(),
+    );
+    Ok((
+        [(header::CONTENT_TYPE, FormatType::Pdf.content_type())],
+        data,
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct ShareParams {
+    format: Option<String>,
+    // 有效期(秒)，为0则不过期，未指定则使用配置的有效期
+    ttl: Option<u64>,
+}
+
+/// 生成图表的签名分享链接，参数与生成图表的接口一致
+async fn chart_share(params: Query<ShareParams>, req: Request<Body>) -> JsonResult<ShareResult> {
+    let value = read_chart_params(req).await?;
+    // 生成链接时先校验参数，避免分享无法生成的图表
+    if is_strict_type(&value) {
+        check_chart_type(&value)?;
+    }
+    let format = FormatType::from(params.format.clone().unwrap_or_default().as_str());
+    check_render_limits(&value, format)?;
+    let result = new_share(&value, format.as_str(), params.ttl.map(Duration::from_secs))?;
+    Ok(Json(result))
+}
+
+#[derive(Deserialize)]
+struct SharedChartParams {
+    sig: String,
+    exp: Option<i64>,
+}
+
+/// 校验签名后生成分享的图表，输出格式由扩展名指定
+async fn shared_chart(
+    method: Method,
+    headers: HeaderMap,
+    Path(file): Path<String>,
+    params: Query<SharedChartParams>,
+) -> HttpResult<Response> {
+    let not_found = || HttpError::new_with_category_status("Not Found", "share", 404);
+    let (id, ext) = file.rsplit_once('.').ok_or_else(not_found)?;
+    let format = FORMAT_TYPES
+        .iter()
+        .find(|format| format.as_str() == ext)
+        .ok_or_else(not_found)?;
+    let value = get_share(id, format.as_str(), &params.sig, params.exp)?;
+    render(&headers, &method, value, *format).await
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+struct BatchItemResult {
+    pub format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    // base64后的图表数据
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<HttpError>,
+}
+
+/// 批量生成图表，每个图表参数中以format指定其输出格式，
+/// 单个图表失败不影响其它图表的生成
+async fn chart_batch(headers: HeaderMap, req: Request<Body>) -> HttpResult<Response> {
+    let buf = read_http_body(req).await?;
+    let items: Vec<serde_json::Value> = serde_json::from_slice(&buf)?;
+    let max_batch_size = must_new_basic_config().max_batch_size as usize;
+    if items.len() > max_batch_size {
+        return Err(HttpError::new_with_category(
+            &format!(
+                "Batch size {} exceeds the limit {max_batch_size}",
+                items.len()
+            ),
+            "batch_too_large",
+        ));
+    }
+
+    // 各图表在线程池中并行生成
+    let mut handles = Vec::with_capacity(items.len());
+    for item in items.into_iter() {
+        let format = FormatType::from(
+            item.get("format")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default(),
+        );
+        handles.push((
+            format,
+            tokio::spawn(async move {
+                let value = expand_template(item)?;
+                check_render_limits(&value, format)?;
+                let timeout = get_render_timeout(&value, format.as_str());
+                spawn_render(format.as_str(), timeout, move || {
+                    render_data(&value, format)
+                })
+                .await
+            }),
+        ));
+    }
+    let mut results = Vec::with_capacity(handles.len());
+    for (format, handle) in handles {
+        let result = match handle.await {
+            Ok(result) => result,
+            Err(err) => Err(HttpError::new_with_category_status(
+                &err.to_string(),
+                "batch_join",
+                500,
+            )),
+        };
+        results.push((format, result));
+    }
+
+    let accept = get_header_value(&headers, header::ACCEPT.as_str());
+    if accept.contains("multipart/mixed") {
+        return Ok(new_multipart_response(results));
+    }
+
+    let items: Vec<BatchItemResult> = results
+        .into_iter()
+        .map(|(format, result)| match result {
+            Ok(data) => BatchItemResult {
+                format: format.as_str().to_string(),
+                content_type: format.content_type().to_str().ok().map(|v| v.to_string()),
+                data: Some(STANDARD.encode(data)),
+                ..Default::default()
+            },
+            Err(err) => BatchItemResult {
+                format: format.as_str().to_string(),
+                error: Some(err),
+                ..Default::default()
+            },
+        })
+        .collect();
+    Ok(Json(items).into_response())
+}
+
+/// 将批量生成的结果以multipart/mixed的形式响应，
+/// 失败的图表以json形式的HttpError作为该部分的内容
+fn new_multipart_response(results: Vec<(FormatType, HttpResult<Bytes>)>) -> Response {
+    let boundary = format!(
+        "charts-batch-{:x}",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    );
+    let mut body = vec![];
+    for (format, result) in results {
+        let (content_type, status, data) = match result {
+            Ok(data) => (format.content_type(), 200, data),
+            Err(err) => {
+                let status = err.status;
+                let data = serde_json::to_vec(&err).unwrap_or_default();
+                (
+                    HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+                    status,
+                    Bytes::from(data),
+                )
+            }
+        };
+        body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        body.extend_from_slice(b"Content-Type: ");
+        body.extend_from_slice(content_type.as_bytes());
+        body.extend_from_slice(format!("\r\nX-Status: {status}\r\n\r\n").as_bytes());
+        body.extend_from_slice(&data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+    let content_type = format!("multipart/mixed; boundary={boundary}");
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+/// 获取图表类型对应的json schema
+async fn get_chart_schema(Path(chart_type): Path<String>) -> JsonResult<serde_json::Value> {
+    let schema = get_schema(&chart_type).ok_or_else(|| {
+        HttpError::new_with_category_status(
+            &format!("Schema of {chart_type} is not found"),
+            "schema",
+            404,
+        )
+    })?;
+    Ok(Json(schema))
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+struct ValidateResult {
+    pub valid: bool,
+    pub problems: Vec<Problem>,
+}
+
+/// 校验图表参数，返回所有的出错信息(包括其json pointer)
+async fn chart_validate(req: Request<Body>) -> JsonResult<ValidateResult> {
+    let data_format = DataFormat::from(req.headers());
+    let buf = read_http_body(req).await?;
+    let value = parse_params(&buf, data_format)?;
+    let problems = validate(&value);
+    Ok(Json(ValidateResult {
+        valid: problems.is_empty(),
+        problems,
+    }))
+}
+
+/// 获取模板
+async fn get_chart_template(Path(name): Path<String>) -> JsonResult<serde_json::Value> {
+    let template = get_template(&name).ok_or_else(|| {
+        HttpError::new_with_category_status(
+            &format!("Template {name} is not found"),
+            "template",
+            404,
+        )
+    })?;
+    Ok(Json(template))
+}
+
+/// 添加或更新模板，模板为部分的图表参数
+async fn put_chart_template(
+    Path(name): Path<String>,
+    req: Request<Body>,
+) -> HttpResult<StatusCode> {
+    let buf = read_http_body(req).await?;
+    let value: serde_json::Value = serde_json::from_slice(&buf)?;
+    add_template(&name, value)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 获取主题
+async fn get_chart_theme(Path(name): Path<String>) -> JsonResult<charts_rs::Theme> {
+    let theme = get_theme(&name).ok_or_else(|| {
+        HttpError::new_with_category_status(&format!("Theme {name} is not found"), "theme", 404)
+    })?;
+    Ok(Json(theme))
+}
+
+/// 添加或更新主题，支持json与yaml(根据content-type判断)
+async fn put_chart_theme(Path(name): Path<String>, req: Request<Body>) -> HttpResult<StatusCode> {
+    let content_type = get_header_value(req.headers(), header::CONTENT_TYPE.as_str());
+    let format = if content_type.contains("yaml") {
+        FileFormat::Yaml
+    } else {
+        FileFormat::Json
+    };
+    let buf = read_http_body(req).await?;
+    let theme = parse_theme(&String::from_utf8_lossy(&buf), format)?;
+    add_theme(&name, theme)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 获取所有字体
+async fn list_chart_fonts() -> JsonResult<Vec<FontInfo>> {
+    Ok(Json(list_fonts()))
+}
+
+#[derive(Deserialize)]
+struct UploadFontParams {
+    name: String,
+}
+
+/// 上传字体，body为字体文件，name为保存的文件名，
+/// 字体需要重启后才生效
+async fn upload_chart_font(
+    params: Query<UploadFontParams>,
+    req: Request<Body>,
+) -> HttpResult<(StatusCode, Json<FontInfo>)> {
+    let font_config = must_new_font_config();
+    if font_config.path.is_empty() {
+        return Err(HttpError::new_with_category_status(
+            "Font upload is disabled, font.path is not set",
+            "font",
+            403,
+        ));
+    }
+    let buf = read_http_body_with_limit(req, font_config.max_size as usize).await?;
+    let info = save_font(&font_config.path, &params.name, &buf)?;
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_chart_type, get_jpeg_matte, get_png_options, is_not_modified, is_strict_type,
+        negotiate_format, parse_hex_color, png_to_jpeg, render_data, render_svg, FormatType,
+        PngOptions,
+    };
+    use axum::http::Method;
+    use charts_rs::{svg_to_png, Color};
+    use image::{load_from_memory_with_format, ImageFormat};
+    use serde_json::json;
+
+    // jpeg文件头
+    static JPEG_MAGIC: [u8; 3] = [0xFF, 0xD8, 0xFF];
+
+    fn new_chart_params(extra: serde_json::Value) -> serde_json::Value {
+        let mut value = json!({
+            "type": "bar",
+            "width": 300,
+            "height": 200,
+            "series_list": [{"name": "Email", "data": [120.0, 132.0, 101.0]}],
+            "x_axis_data": ["Mon", "Tue", "Wed"],
+        });
+        if let (Some(map), Some(extra)) = (value.as_object_mut(), extra.as_object()) {
+            map.extend(extra.clone());
+        }
+        value
+    }
+
+    /// 解码jpeg并返回左上角的像素
+    fn get_corner_pixel(data: &[u8]) -> [u8; 3] {
+        assert_eq!(JPEG_MAGIC, data[..3]);
+        let rgb = load_from_memory_with_format(data, ImageFormat::Jpeg)
+            .unwrap()
+            .to_rgb8();
+        rgb.get_pixel(0, 0).0
+    }
+
+    #[test]
+    fn parse_hex_color_with_alpha() {
+        let color = parse_hex_color("#00000000").unwrap();
+        assert!(color.is_transparent());
+        let color = parse_hex_color("#336699").unwrap();
+        assert_eq!(
+            (0x33, 0x66, 0x99, 255),
+            (color.r, color.g, color.b, color.a)
+        );
+        let color = parse_hex_color("#f008").unwrap();
+        assert_eq!((255, 0, 0, 0x88), (color.r, color.g, color.b, color.a));
+        assert!(parse_hex_color("336699").is_none());
+        assert!(parse_hex_color("#33669").is_none());
+        assert!(parse_hex_color("#zzzzzz").is_none());
+    }
+
+    #[test]
+    fn png_to_jpeg_fills_matte() {
+        // 无背景的svg，生成的png完全透明，转换后应为填充色
+        let png = svg_to_png(
+            r#"<svg width="32" height="32" viewBox="0 0 32 32" xmlns="http://www.w3.org/2000/svg"></svg>"#,
+        )
+        .unwrap();
+        // 灰色经YCbCr转换后无误差，因此可以直接比较
+        let matte = parse_hex_color("#808080").unwrap();
+        let data = png_to_jpeg(&png, matte, 100).unwrap();
+        assert_eq!([matte.r, matte.g, matte.b], get_corner_pixel(&data));
+    }
+
+    #[test]
+    fn render_jpeg_transparent_theme() {
+        // 主题的背景色透明时使用配置的填充色(默认为白色)
+        let mut theme = charts_rs::get_theme("light").as_ref().clone();
+        theme.background_color = Color::transparent();
+        charts_rs::add_theme("jpeg_transparent", theme);
+        let value = new_chart_params(json!({"theme": "jpeg_transparent", "quality": 100}));
+        let matte = get_jpeg_matte(&value);
+        assert_eq!(Color::white(), matte);
+
+        let data = render_data(&value, FormatType::Jpeg).unwrap();
+        assert_eq!([matte.r, matte.g, matte.b], get_corner_pixel(&data));
+    }
+
+    #[test]
+    fn render_jpeg_opaque_theme() {
+        // 主题的背景色不透明时填充色即为背景色
+        let background_color = charts_rs::get_theme("dark").background_color;
+        let value = new_chart_params(json!({"theme": "dark"}));
+        assert_eq!(background_color, get_jpeg_matte(&value));
+    }
+
+    #[test]
+    fn render_jpeg_quality_bounds() {
+        let render = |quality: serde_json::Value| {
+            let value = new_chart_params(json!({ "quality": quality }));
+            let data = render_data(&value, FormatType::Jpeg).unwrap();
+            assert_eq!(JPEG_MAGIC, data[..3]);
+            data.len()
+        };
+        let default_size = render(json!(null));
+        // 100为有效的质量，不能被当作默认值
+        assert!(render(json!(100)) > default_size);
+        assert!(render(json!(1)) < default_size);
+        // 超出范围的使用默认值
+        assert_eq!(default_size, render(json!(0)));
+        assert_eq!(default_size, render(json!(101)));
+    }
+
+    #[test]
+    fn not_modified_only_for_get() {
+        let entity_tag = r#""abc""#;
+        assert!(is_not_modified(&Method::GET, r#""abc""#, entity_tag, false));
+        assert!(is_not_modified(
+            &Method::HEAD,
+            r#""xyz", W/"abc""#,
+            entity_tag,
+            false
+        ));
+        assert!(!is_not_modified(&Method::GET, r#""xyz""#, entity_tag, true));
+        assert!(!is_not_modified(&Method::GET, "", entity_tag, true));
+        // 非GET与HEAD的请求忽略If-None-Match
+        assert!(!is_not_modified(
+            &Method::POST,
+            r#""abc""#,
+            entity_tag,
+            true
+        ));
+        assert!(!is_not_modified(&Method::POST, "*", entity_tag, true));
+        // *仅在已生成时匹配
+        assert!(is_not_modified(&Method::GET, "*", entity_tag, true));
+        assert!(!is_not_modified(&Method::GET, "*", entity_tag, false));
+    }
+
+    #[test]
+    fn negotiate_exact_format() {
+        assert_eq!(Some(FormatType::Png), negotiate_format("image/png"));
+        assert_eq!(Some(FormatType::Pdf), negotiate_format("application/pdf"));
+        assert_eq!(
+            Some(FormatType::Jpeg),
+            negotiate_format("text/html, IMAGE/JPEG")
+        );
+        assert_eq!(None, negotiate_format("text/html"));
+    }
+
+    #[test]
+    fn negotiate_format_by_q() {
+        // q值高的优先
+        assert_eq!(
+            Some(FormatType::Avif),
+            negotiate_format("image/webp;q=0.5, image/avif")
+        );
+        assert_eq!(
+            Some(FormatType::Webp),
+            negotiate_format("image/png; q=0.2, image/webp; q=0.8")
+        );
+        // q值相同则按accept中的顺序
+        assert_eq!(
+            Some(FormatType::Webp),
+            negotiate_format("image/webp, image/png")
+        );
+        // q=0表示不接受
+        assert_eq!(None, negotiate_format("image/png;q=0"));
+    }
+
+    #[test]
+    fn negotiate_wildcard_format() {
+        // 通配符按支持的格式顺序选择
+        assert_eq!(Some(FormatType::Svg), negotiate_format(""));
+        assert_eq!(Some(FormatType::Svg), negotiate_format("*/*"));
+        assert_eq!(Some(FormatType::Svg), negotiate_format("image/*"));
+        assert_eq!(Some(FormatType::Pdf), negotiate_format("application/*"));
+        // 通配符不选择被q=0排除的格式
+        assert_eq!(
+            Some(FormatType::Png),
+            negotiate_format("image/*, image/svg+xml;q=0")
+        );
+        assert_eq!(
+            Some(FormatType::Webp),
+            negotiate_format("image/svg+xml;q=0, image/png;q=0, */*;q=0.5")
+        );
+        // 明确的格式优先于q值较低的通配符
+        assert_eq!(
+            Some(FormatType::Avif),
+            negotiate_format("*/*;q=0.1, image/avif")
+        );
+    }
+
+    #[test]
+    fn check_supported_chart_type() {
+        assert!(check_chart_type(&json!({})).is_ok());
+        assert!(check_chart_type(&json!({"type": ""})).is_ok());
+        assert!(check_chart_type(&json!({"type": "line"})).is_ok());
+        let err = check_chart_type(&json!({"type": "donut"})).unwrap_err();
+        assert_eq!(400, err.status);
+        assert_eq!("unsupported_chart_type", err.category);
+        assert!(err.extra.unwrap().contains(&"bar".to_string()));
+        assert!(check_chart_type(&json!({"type": 1})).is_err());
+        // 子图表同样校验
+        let value = json!({
+            "type": "multi_chart",
+            "child_charts": [{"type": "bar"}, {"type": "donut"}],
+        });
+        assert!(check_chart_type(&value).is_err());
+    }
+
+    #[test]
+    fn render_strict_chart_type() {
+        // 默认为严格模式，请求参数可覆盖
+        assert!(is_strict_type(&json!({})));
+        assert!(!is_strict_type(&json!({"strict": false})));
+
+        let err = render_svg(&new_chart_params(json!({"type": "donut"}))).unwrap_err();
+        assert_eq!("unsupported_chart_type", err.category);
+        // 非严格模式下不支持的类型以bar生成
+        let value = new_chart_params(json!({"type": "donut", "strict": false}));
+        let bar = render_svg(&new_chart_params(json!({}))).unwrap();
+        assert_eq!(bar, render_svg(&value).unwrap());
+    }
+
+    #[test]
+    fn render_absent_chart_type() {
+        // 未指定类型则为bar
+        let mut value = new_chart_params(json!({}));
+        let bar = render_svg(&value).unwrap();
+        value.as_object_mut().unwrap().remove("type");
+        assert_eq!(bar, render_svg(&value).unwrap());
+    }
+
+    #[test]
+    fn parse_png_options() {
+        assert_eq!(PngOptions::default(), get_png_options(&json!({})).unwrap());
+        let options = get_png_options(&json!({
+            "png": {"max_colors": 16, "dithering": 0.5, "speed": 3, "compression_level": 9}
+        }))
+        .unwrap();
+        assert_eq!(Some(16), options.max_colors);
+        assert_eq!(Some(9), options.compression_level);
+        assert!(!options.is_default());
+    }
+
+    #[test]
+    fn reject_invalid_png_options() {
+        let invalid = [
+            json!({"max_color": 16}),
+            json!({"max_colors": 1}),
+            json!({"max_colors": 257}),
+            json!({"dithering": 1.5}),
+            json!({"speed": 0}),
+            json!({"compression_level": 10}),
+            json!({"speed": "fast"}),
+        ];
+        for png in invalid {
+            let err = get_png_options(&json!({ "png": png })).unwrap_err();
+            assert_eq!("png", err.category, "{png}");
+            assert_eq!(400, err.status, "{png}");
+        }
+    }
+}
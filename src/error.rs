@@ -5,6 +5,8 @@ use axum::{BoxError, Json};
 use serde::{Deserialize, Serialize};
 use tracing::error;
 
+use crate::metrics::observe_http_error;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpError {
     // 出错信息
@@ -95,6 +97,7 @@ impl IntoResponse for HttpError {
             Ok(status) => status,
             Err(_) => StatusCode::BAD_REQUEST,
         };
+        observe_http_error(&self.category);
         // 对于出错设置为no-cache
         let mut res = Json(self).into_response();
         res.headers_mut()
@@ -0,0 +1,204 @@
+use glob::glob;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tracing::{error, info};
+
+use crate::error::{HttpError, HttpResult};
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct FontInfo {
+    // 字体名称(与charts-rs一致，去除字重后缀)
+    pub family: String,
+    // 字体完整名称
+    pub full_name: String,
+    // normal, italic或oblique，内置字体为空
+    pub style: String,
+    // 字重，内置字体为0
+    pub weight: u16,
+    // 字形数量
+    pub glyphs: u16,
+    // 支持的unicode字符数量
+    pub codepoints: usize,
+    // 字体文件，内置字体为空
+    pub file: String,
+    // 是否已加载，上传的字体需重启后才加载
+    pub loaded: bool,
+}
+
+static FONTS: Lazy<RwLock<Vec<FontInfo>>> = Lazy::new(|| RwLock::new(vec![]));
+
+/// 字体名称去除字重后缀，与charts-rs的处理一致
+fn get_family(full_name: &str) -> String {
+    let mut family = full_name.to_string();
+    for weight in ["Thin", "Light", "Regular", "Medium", "Bold"] {
+        family = family.replace(weight, "");
+    }
+    if let Some(value) = family.strip_suffix("Black") {
+        family = value.to_string();
+    }
+    family.trim().to_string()
+}
+
+/// 解析字体文件，获取字体的相关信息
+pub fn parse_font(data: &[u8], file: &str) -> HttpResult<FontInfo> {
+    let face = ttf_parser::Face::parse(data, 0)
+        .map_err(|err| HttpError::new_with_category(&err.to_string(), "font"))?;
+    let full_name = face
+        .names()
+        .into_iter()
+        .find(|name| name.name_id == ttf_parser::name_id::FULL_NAME && name.is_unicode())
+        .and_then(|name| name.to_string())
+        .unwrap_or_default();
+    let family = get_family(&full_name);
+    if family.is_empty() {
+        return Err(HttpError::new_with_category("Font family is empty", "font"));
+    }
+    let mut codepoints = HashSet::new();
+    if let Some(cmap) = face.tables().cmap {
+        for subtable in cmap.subtables {
+            if subtable.is_unicode() {
+                subtable.codepoints(|c| {
+                    codepoints.insert(c);
+                });
+            }
+        }
+    }
+    let style = match face.style() {
+        ttf_parser::Style::Normal => "normal",
+        ttf_parser::Style::Italic => "italic",
+        ttf_parser::Style::Oblique => "oblique",
+    };
+    Ok(FontInfo {
+        family,
+        full_name,
+        style: style.to_string(),
+        weight: face.weight().to_number(),
+        glyphs: face.number_of_glyphs(),
+        codepoints: codepoints.len(),
+        file: file.to_string(),
+        loaded: false,
+    })
+}
+
+/// 从目录中查找字体文件(ttf与otf)
+fn find_font_files(dir: &str) -> Vec<PathBuf> {
+    let mut font_files = vec![];
+    let file_paths = [
+        format!(r#"{dir}/*.ttf"#),
+        format!(r#"{dir}/*.otf"#),
+        format!(r#"{dir}/**/*.ttf"#),
+        format!(r#"{dir}/**/*.otf"#),
+    ];
+    for file_path in file_paths.iter() {
+        for entry in glob(file_path)
+            .expect("Failed to read glob pattern")
+            .flatten()
+        {
+            if !font_files.contains(&entry) {
+                font_files.push(entry)
+            }
+        }
+    }
+    font_files
+}
+
+/// 从多个目录中加载字体并初始化charts-rs的字体，
+/// 字体加载失败时输出日志并忽略该字体
+pub fn load_fonts(dirs: &[String]) {
+    let mut infos = vec![];
+    let mut font_buffers = vec![];
+    for dir in dirs.iter() {
+        for item in find_font_files(dir).iter() {
+            let file = item.to_string_lossy().to_string();
+            let result = fs::read(item)
+                .map_err(|err| HttpError::new_with_category(&err.to_string(), "font"))
+                .and_then(|buf| parse_font(&buf, &file).map(|info| (info, buf)))
+                .and_then(|(info, buf)| {
+                    // charts-rs有任一字体加载失败则全部失败，因此先校验单个字体
+                    fontdue::Font::from_bytes(buf.as_slice(), fontdue::FontSettings::default())
+                        .map_err(|err| HttpError::new_with_category(err, "font"))?;
+                    Ok((info, buf))
+                });
+            match result {
+                Ok((info, buf)) => {
+                    info!(family = info.family, file, "load font success");
+                    infos.push(info);
+                    font_buffers.push(buf);
+                }
+                Err(err) => error!(file, error = err.message, "load font fail"),
+            }
+        }
+    }
+    let arr: Vec<&[u8]> = font_buffers.iter().map(|item| item.as_slice()).collect();
+    if let Err(err) = charts_rs::get_or_try_init_fonts(Some(arr)) {
+        // 字体均已校验，正常不会出错，若出错则仅使用内置字体
+        error!(error = err.to_string(), "init fonts fail, use default font");
+        infos.clear();
+        if let Err(err) = charts_rs::get_or_try_init_fonts(None) {
+            error!(error = err.to_string(), "init default font fail");
+        }
+    }
+    if let Ok(mut fonts) = FONTS.write() {
+        for mut info in infos {
+            info.loaded = true;
+            fonts.push(info);
+        }
+    }
+}
+
+/// 获取所有字体，包括内置字体以及待重启加载的字体
+pub fn list_fonts() -> Vec<FontInfo> {
+    let mut result = vec![];
+    if let Ok(font) = charts_rs::get_font(charts_rs::DEFAULT_FONT_FAMILY) {
+        result.push(FontInfo {
+            family: charts_rs::DEFAULT_FONT_FAMILY.to_string(),
+            full_name: font.name().unwrap_or_default().to_string(),
+            glyphs: font.glyph_count(),
+            codepoints: font.chars().len(),
+            loaded: true,
+            ..Default::default()
+        });
+    }
+    if let Ok(fonts) = FONTS.read() {
+        result.extend(fonts.iter().cloned());
+    }
+    result
+}
+
+/// 保存上传的字体，由于charts-rs的字体保存在OnceCell中，初始化后无法再添加，
+/// 因此上传的字体需重启后才生效(返回的loaded为false)
+pub fn save_font(dir: &str, name: &str, data: &[u8]) -> HttpResult<FontInfo> {
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && (name.ends_with(".ttf") || name.ends_with(".otf"));
+    if !valid_name {
+        return Err(HttpError::new_with_category(
+            &format!("Font file name {name} is invalid, it should be *.ttf or *.otf"),
+            "font",
+        ));
+    }
+    let file = Path::new(dir).join(name);
+    if file.exists() {
+        return Err(HttpError::new_with_category_status(
+            &format!("Font file {name} already exists"),
+            "font",
+            409,
+        ));
+    }
+    let file = file.to_string_lossy().to_string();
+    let info = parse_font(data, &file)?;
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&file, data))
+        .map_err(|err| HttpError::new_with_category(&err.to_string(), "font"))?;
+    info!(family = info.family, file, "save font success");
+    if let Ok(mut fonts) = FONTS.write() {
+        fonts.push(info.clone());
+    }
+    Ok(info)
+}
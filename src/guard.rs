@@ -0,0 +1,189 @@
+use serde_json::Value;
+
+use crate::config::BasicConfig;
+use crate::error::{HttpError, HttpResult};
+
+fn new_limit_error(message: &str, category: &str) -> HttpError {
+    HttpError::new_with_category_status(message, category, 422)
+}
+
+/// 校验单个图表的宽高、像素数、series数量以及数据点数量
+fn check_chart(value: &Value, scale: f64, config: &BasicConfig) -> HttpResult<()> {
+    let width = value.get("width").and_then(|v| v.as_f64()).unwrap_or(600.0);
+    let height = value
+        .get("height")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(400.0);
+    if width > config.max_width as f64 || height > config.max_height as f64 {
+        return Err(new_limit_error(
+            &format!(
+                "Chart size {width}x{height} exceeds the limit {}x{}",
+                config.max_width, config.max_height
+            ),
+            "chart_too_large",
+        ));
+    }
+    let pixels = (width * scale).ceil() * (height * scale).ceil();
+    if pixels > config.max_pixels as f64 {
+        return Err(new_limit_error(
+            &format!(
+                "Image pixels {pixels} exceed the limit {}",
+                config.max_pixels
+            ),
+            "too_many_pixels",
+        ));
+    }
+
+    let series_list = value
+        .get("series_list")
+        .and_then(|v| v.as_array())
+        .map(|v| v.as_slice())
+        .unwrap_or_default();
+    if series_list.len() > config.max_series as usize {
+        return Err(new_limit_error(
+            &format!(
+                "Series count {} exceeds the limit {}",
+                series_list.len(),
+                config.max_series
+            ),
+            "too_many_series",
+        ));
+    }
+    // 普通图表的series.data，heatmap的series.data以及table的data
+    let mut data_list: Vec<&Value> = series_list
+        .iter()
+        .filter_map(|series| series.get("data"))
+        .collect();
+    if let Some(data) = value.get("series").and_then(|v| v.get("data")) {
+        data_list.push(data);
+    }
+    if let Some(data) = value.get("data") {
+        data_list.push(data);
+    }
+    for data in data_list {
+        let count = data.as_array().map(|v| v.len()).unwrap_or_default();
+        if count > config.max_points as usize {
+            return Err(new_limit_error(
+                &format!("Data points {count} exceed the limit {}", config.max_points),
+                "too_many_points",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 在生成图表前校验参数是否超出限制，超出则返回422，
+/// multi_chart的子图表同样校验
+pub fn check_chart_limits(value: &Value, scale: f32, config: &BasicConfig) -> HttpResult<()> {
+    check_chart(value, scale as f64, config)?;
+    if let Some(child_charts) = value.get("child_charts").and_then(|v| v.as_array()) {
+        if child_charts.len() > config.max_series as usize {
+            return Err(new_limit_error(
+                &format!(
+                    "Child chart count {} exceeds the limit {}",
+                    child_charts.len(),
+                    config.max_series
+                ),
+                "too_many_series",
+            ));
+        }
+        for item in child_charts.iter() {
+            check_chart(item, scale as f64, config)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_chart_limits;
+    use crate::config::must_new_basic_config;
+    use serde_json::{json, Value};
+
+    fn get_category(value: &Value, scale: f32) -> String {
+        let err = check_chart_limits(value, scale, must_new_basic_config()).unwrap_err();
+        assert_eq!(422, err.status);
+        err.category
+    }
+
+    #[test]
+    fn check_chart_size() {
+        let config = must_new_basic_config();
+        assert!(check_chart_limits(&json!({}), 1.0, config).is_ok());
+        let value = json!({
+            "width": config.max_width + 1,
+            "height": 400,
+        });
+        assert_eq!("chart_too_large", get_category(&value, 1.0));
+    }
+
+    #[test]
+    fn check_chart_pixels() {
+        let config = must_new_basic_config();
+        // 宽高未超出限制，但缩放后像素数超出
+        let value = json!({
+            "width": config.max_width,
+            "height": config.max_height,
+        });
+        assert_eq!("too_many_pixels", get_category(&value, 1.0));
+        let value = json!({
+            "width": 4000,
+            "height": 3000,
+        });
+        assert!(check_chart_limits(&value, 1.0, config).is_ok());
+        assert_eq!("too_many_pixels", get_category(&value, 2.0));
+    }
+
+    #[test]
+    fn check_chart_series() {
+        let config = must_new_basic_config();
+        let series_list: Vec<Value> = (0..=config.max_series)
+            .map(|_| json!({"name": "Email", "data": [1.0]}))
+            .collect();
+        let value = json!({
+            "series_list": series_list,
+        });
+        assert_eq!("too_many_series", get_category(&value, 1.0));
+    }
+
+    #[test]
+    fn check_chart_points() {
+        let config = must_new_basic_config();
+        let data = vec![1.0; config.max_points as usize + 1];
+        for value in [
+            json!({"series_list": [{"name": "Email", "data": data}]}),
+            json!({"series": {"data": data}}),
+            json!({"data": data}),
+        ] {
+            assert_eq!("too_many_points", get_category(&value, 1.0));
+        }
+        let data = vec![1.0; config.max_points as usize];
+        let value = json!({"series_list": [{"name": "Email", "data": data}]});
+        assert!(check_chart_limits(&value, 1.0, config).is_ok());
+    }
+
+    #[test]
+    fn check_child_charts() {
+        let config = must_new_basic_config();
+        let child_charts: Vec<Value> = (0..=config.max_series).map(|_| json!({})).collect();
+        let value = json!({
+            "child_charts": child_charts,
+        });
+        assert_eq!("too_many_series", get_category(&value, 1.0));
+
+        // 子图表同样校验
+        let value = json!({
+            "child_charts": [
+                {},
+                {"width": config.max_width + 1},
+            ],
+        });
+        assert_eq!("chart_too_large", get_category(&value, 1.0));
+        let value = json!({
+            "child_charts": [
+                {"data": vec![1.0; config.max_points as usize + 1]},
+            ],
+        });
+        assert_eq!("too_many_points", get_category(&value, 1.0));
+    }
+}
@@ -0,0 +1,402 @@
+use axum::http::{header, HeaderMap};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::error::{HttpError, HttpResult};
+use crate::schema::is_text_field;
+use crate::util::get_header_value;
+
+// 通过header指定图表参数(json对象)，用于csv等非json的数据
+pub static CHART_OPTIONS_HEADER: &str = "X-Chart-Options";
+
+// 接口自身使用的query参数(如api key、分享的格式与有效期)，不作为图表参数，
+// 避免api key等写入分享链接的id中。scale与quality为生成参数，仍通过query指定
+static RESERVED_QUERY_KEYS: [&str; 4] = ["key", "format", "ttl", "enc"];
+
+/// 请求数据的格式，根据content-type判断
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    Json,
+    Yaml,
+    Toml,
+    Csv,
+    Tsv,
+}
+
+impl From<&HeaderMap> for DataFormat {
+    fn from(headers: &HeaderMap) -> Self {
+        let content_type = get_header_value(headers, header::CONTENT_TYPE.as_str());
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        match mime.as_str() {
+            "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => {
+                DataFormat::Yaml
+            }
+            "application/toml" => DataFormat::Toml,
+            "text/csv" => DataFormat::Csv,
+            "text/tab-separated-values" => DataFormat::Tsv,
+            // 未指定或其它的类型均以json处理
+            _ => DataFormat::Json,
+        }
+    }
+}
+
+/// 出错信息的extra中记录出错的行与列
+fn new_parse_error(message: &str, category: &str, line: u64, column: Option<usize>) -> HttpError {
+    let mut extra = vec![format!("line:{line}")];
+    if let Some(column) = column {
+        extra.push(format!("column:{column}"));
+    }
+    HttpError {
+        extra: Some(extra),
+        ..HttpError::new_with_category(message, category)
+    }
+}
+
+fn new_csv_error(message: &str, line: u64, column: Option<usize>) -> HttpError {
+    new_parse_error(message, "csv", line, column)
+}
+
+/// 根据字节的偏移获取所在的行与列(从1开始)
+fn get_line_column(data: &str, offset: usize) -> (u64, usize) {
+    let before = data.get(..offset).unwrap_or(data);
+    let line = before.matches('\n').count() as u64 + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|value| value.chars().count())
+        .unwrap_or_default()
+        + 1;
+    (line, column)
+}
+
+/// 以json、yaml或toml解析图表参数，统一转换为json value，
+/// 解析出错时extra中记录出错的行与列
+pub fn parse_chart_params(data: &[u8], format: DataFormat) -> HttpResult<Value> {
+    match format {
+        DataFormat::Yaml => serde_yaml::from_slice::<Value>(data).map_err(|err| {
+            if let Some(location) = err.location() {
+                new_parse_error(
+                    &err.to_string(),
+                    "yaml",
+                    location.line() as u64,
+                    Some(location.column()),
+                )
+            } else {
+                HttpError::new_with_category(&err.to_string(), "yaml")
+            }
+        }),
+        DataFormat::Toml => {
+            let data = std::str::from_utf8(data)
+                .map_err(|err| HttpError::new_with_category(&err.to_string(), "toml"))?;
+            toml::from_str::<Value>(data).map_err(|err| {
+                let message = err.message().to_string();
+                if let Some(span) = err.span() {
+                    let (line, column) = get_line_column(data, span.start);
+                    new_parse_error(&message, "toml", line, Some(column))
+                } else {
+                    HttpError::new_with_category(&message, "toml")
+                }
+            })
+        }
+        _ => serde_json::from_slice::<Value>(data).map_err(|err| {
+            if err.line() == 0 {
+                HttpError::from(err)
+            } else {
+                new_parse_error(
+                    &err.to_string(),
+                    "json",
+                    err.line() as u64,
+                    Some(err.column()),
+                )
+            }
+        }),
+    }
+}
+
+/// 从header与query中获取图表参数，query中的值优先，
+/// 文本类型的字段(如title_text)保留原始字符串，
+/// 其它字段若为合法的json(如数值、数组)则以json解析，否则为字符串
+pub fn get_chart_options(
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+) -> HttpResult<Map<String, Value>> {
+    let mut options = Map::new();
+    let value = get_header_value(headers, CHART_OPTIONS_HEADER);
+    if !value.is_empty() {
+        match serde_json::from_str::<Value>(&value)? {
+            Value::Object(map) => options = map,
+            _ => {
+                return Err(HttpError::new_with_category(
+                    &format!("{CHART_OPTIONS_HEADER} should be a json object"),
+                    "csv",
+                ))
+            }
+        }
+    }
+    let chart_type = query
+        .get("type")
+        .cloned()
+        .or_else(|| {
+            options
+                .get("type")
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string())
+        })
+        .unwrap_or_default();
+    for (key, value) in query.iter() {
+        if RESERVED_QUERY_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let value = if is_text_field(&chart_type, key) {
+            Value::String(value.clone())
+        } else {
+            serde_json::from_str::<Value>(value).unwrap_or(Value::String(value.clone()))
+        };
+        options.insert(key.clone(), value);
+    }
+    Ok(options)
+}
+
+/// 将csv(tsv)数据转换为图表参数，首行为标题，
+/// 第一列为x轴数据，其余的每列为一个series，
+/// 若图表类型为table则所有数据均作为表格数据
+pub fn csv_to_chart_params(
+    data: &[u8],
+    delimiter: u8,
+    mut options: Map<String, Value>,
+) -> HttpResult<Value> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record.map_err(|err| {
+            let line = err.position().map(|pos| pos.line()).unwrap_or_default();
+            new_csv_error(&err.to_string(), line, None)
+        })?;
+        let line = record.position().map(|pos| pos.line()).unwrap_or_default();
+        rows.push((line, record));
+    }
+    if rows.is_empty() {
+        return Err(HttpError::new_with_category("Csv data is empty", "csv"));
+    }
+
+    let is_table = options.get("type").and_then(|value| value.as_str()) == Some("table");
+    if is_table {
+        let data: Vec<Vec<String>> = rows
+            .iter()
+            .map(|(_, record)| record.iter().map(|item| item.to_string()).collect())
+            .collect();
+        options.insert("data".to_string(), serde_json::to_value(data)?);
+        return Ok(Value::Object(options));
+    }
+
+    let (_, header) = &rows[0];
+    let mut x_axis_data = vec![];
+    let mut series_data: Vec<Vec<f32>> = vec![vec![]; header.len().saturating_sub(1)];
+    for (line, record) in rows.iter().skip(1) {
+        for (index, item) in record.iter().enumerate() {
+            if index == 0 {
+                x_axis_data.push(item.to_string());
+                continue;
+            }
+            // 空值则为charts-rs的空值，不展示该点，
+            // NaN与inf无法以json表示，因此也视为出错
+            let value = if item.is_empty() {
+                charts_rs::NIL_VALUE
+            } else {
+                item.parse::<f32>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| {
+                        new_csv_error(
+                            &format!("Value {item} is not a number"),
+                            *line,
+                            Some(index + 1),
+                        )
+                    })?
+            };
+            series_data[index - 1].push(value);
+        }
+    }
+    let series_list: Vec<Value> = header
+        .iter()
+        .skip(1)
+        .zip(series_data)
+        .map(|(name, data)| serde_json::json!({ "name": name, "data": data }))
+        .collect();
+    options.insert(
+        "x_axis_data".to_string(),
+        serde_json::to_value(x_axis_data)?,
+    );
+    options.insert("series_list".to_string(), Value::Array(series_list));
+    Ok(Value::Object(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        csv_to_chart_params, get_chart_options, get_line_column, parse_chart_params, DataFormat,
+    };
+    use axum::http::{header, HeaderMap};
+    use serde_json::{json, Map};
+    use std::collections::HashMap;
+
+    #[test]
+    fn query_options_keep_text_fields() {
+        let query: HashMap<String, String> = [
+            ("title_text", "2024"),
+            ("width", "800"),
+            ("legend_show", "false"),
+            ("type", "line"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let options = get_chart_options(&HeaderMap::new(), &query).unwrap();
+        assert_eq!(json!("2024"), options["title_text"]);
+        assert_eq!(json!(800), options["width"]);
+        assert_eq!(json!(false), options["legend_show"]);
+        assert_eq!(json!("line"), options["type"]);
+    }
+
+    #[test]
+    fn query_options_skip_reserved_keys() {
+        let query: HashMap<String, String> = [
+            ("key", "secret"),
+            ("format", "png"),
+            ("width", "800"),
+            ("scale", "2"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let options = get_chart_options(&HeaderMap::new(), &query).unwrap();
+        assert!(!options.contains_key("key"));
+        assert!(!options.contains_key("format"));
+        assert_eq!(json!(800), options["width"]);
+        assert_eq!(json!(2), options["scale"]);
+    }
+
+    #[test]
+    fn csv_to_series() {
+        let data = "name,Email,Video\nMon,120,220\nTue,,182.5\n";
+        let value = csv_to_chart_params(data.as_bytes(), b',', Map::new()).unwrap();
+        assert_eq!(json!(["Mon", "Tue"]), value["x_axis_data"]);
+        assert_eq!(
+            json!([
+                {"name": "Email", "data": [120.0, charts_rs::NIL_VALUE]},
+                {"name": "Video", "data": [220.0, 182.5]},
+            ]),
+            value["series_list"]
+        );
+
+        let data = "name\tEmail\nMon\t120\n";
+        let value = csv_to_chart_params(data.as_bytes(), b'\t', Map::new()).unwrap();
+        assert_eq!(
+            json!([{"name": "Email", "data": [120.0]}]),
+            value["series_list"]
+        );
+    }
+
+    #[test]
+    fn csv_to_table() {
+        let mut options = Map::new();
+        options.insert("type".to_string(), json!("table"));
+        let data = "Name,Price\nApple,1.5\n";
+        let value = csv_to_chart_params(data.as_bytes(), b',', options).unwrap();
+        // 表格的数据均保留为字符串
+        assert_eq!(json!([["Name", "Price"], ["Apple", "1.5"]]), value["data"]);
+        assert!(value.get("series_list").is_none());
+    }
+
+    #[test]
+    fn csv_invalid_data() {
+        let err = csv_to_chart_params(b"", b',', Map::new()).unwrap_err();
+        assert_eq!("csv", err.category);
+
+        // 每行的列数不一致
+        let data = "name,Email\nMon,120\nTue,132,101\n";
+        let err = csv_to_chart_params(data.as_bytes(), b',', Map::new()).unwrap_err();
+        assert_eq!("csv", err.category);
+        assert_eq!(Some(vec!["line:3".to_string()]), err.extra);
+
+        for item in ["abc", "NaN", "inf"] {
+            let data = format!("name,Email,Video\nMon,120,220\nTue,132,{item}\n");
+            let err = csv_to_chart_params(data.as_bytes(), b',', Map::new()).unwrap_err();
+            assert_eq!(format!("Value {item} is not a number"), err.message);
+            assert_eq!(
+                Some(vec!["line:3".to_string(), "column:3".to_string()]),
+                err.extra
+            );
+        }
+    }
+
+    #[test]
+    fn data_format_from_content_type() {
+        let format = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, value.parse().unwrap());
+            DataFormat::from(&headers)
+        };
+        assert_eq!(
+            DataFormat::Yaml,
+            format("application/x-yaml; charset=utf-8")
+        );
+        assert_eq!(DataFormat::Toml, format("Application/TOML"));
+        assert_eq!(DataFormat::Csv, format("text/csv"));
+        assert_eq!(DataFormat::Json, format("text/plain"));
+        assert_eq!(DataFormat::Json, DataFormat::from(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn line_column() {
+        let data = "a = 1\nb = 中文\n";
+        assert_eq!((1, 1), get_line_column(data, 0));
+        assert_eq!((2, 5), get_line_column(data, 10));
+        // 列按字符计算
+        assert_eq!((2, 6), get_line_column(data, 13));
+    }
+
+    #[test]
+    fn parse_params() {
+        let expected = json!({"type": "bar", "width": 600});
+        let value = parse_chart_params(b"type: bar\nwidth: 600\n", DataFormat::Yaml).unwrap();
+        assert_eq!(expected, value);
+        let value = parse_chart_params(b"type = \"bar\"\nwidth = 600\n", DataFormat::Toml).unwrap();
+        assert_eq!(expected, value);
+        let value = parse_chart_params(br#"{"type":"bar","width":600}"#, DataFormat::Json).unwrap();
+        assert_eq!(expected, value);
+    }
+
+    #[test]
+    fn parse_params_error_location() {
+        let err = parse_chart_params(b"type: bar\nwidth: [600\n", DataFormat::Yaml).unwrap_err();
+        assert_eq!("yaml", err.category);
+        let extra = err.extra.unwrap();
+        assert_eq!("line:3", extra[0]);
+        assert!(extra[1].starts_with("column:"));
+
+        let err =
+            parse_chart_params(b"type = \"bar\"\nwidth = 6 00\n", DataFormat::Toml).unwrap_err();
+        assert_eq!("toml", err.category);
+        assert_eq!(
+            Some(vec!["line:2".to_string(), "column:9".to_string()]),
+            err.extra
+        );
+
+        let err = parse_chart_params(b"{\n  \"type\": bar\n}", DataFormat::Json).unwrap_err();
+        assert_eq!("json", err.category);
+        assert_eq!(
+            Some(vec!["line:2".to_string(), "column:11".to_string()]),
+            err.extra
+        );
+    }
+}
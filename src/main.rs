@@ -1,10 +1,9 @@
-use axum::{error_handling::HandleErrorLayer, middleware::from_fn, Router};
+use axum::middleware::{from_fn, from_fn_with_state};
+use axum::{error_handling::HandleErrorLayer, Router};
 use axum_client_ip::ClientIpSource;
-use glob::glob;
 use std::net::SocketAddr;
 use std::time::Duration;
-use std::{env, fs, str::FromStr};
-use substring::Substring;
+use std::{env, str::FromStr};
 use tokio::signal;
 use tower::ServiceBuilder;
 use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
@@ -14,11 +13,24 @@ use tracing::info;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
+mod cache;
+mod codec;
 mod config;
 mod controller;
 mod dist;
 mod error;
+mod font;
+mod guard;
+mod ingest;
+mod metrics;
 mod middleware;
+mod pdf;
+mod pool;
+mod report;
+mod schema;
+mod share;
+mod template;
+mod theme;
 mod util;
 
 fn init_logger() {
@@ -76,13 +88,19 @@ async fn run() {
         .and(NotForContentType::IMAGES)
         .and(NotForContentType::SSE);
 
+    let basic_config = config::must_new_basic_config();
+    // 整体的超时不小于图表生成的超时
+    let timeout = config::must_new_timeout_config()
+        .max_timeout()
+        .max(Duration::from_secs(30));
+
     // build our application with a route
     let app = Router::new()
         .merge(controller::new_router())
         .layer(
             ServiceBuilder::new()
                 .layer(HandleErrorLayer::new(error::handle_error))
-                .timeout(Duration::from_secs(30)),
+                .timeout(timeout),
         )
         // 后面的layer先执行
         .layer(
@@ -91,13 +109,19 @@ async fn run() {
                 .layer(CompressionLayer::new().compress_when(predicate))
                 .layer(from_fn(middleware::access_log))
                 .layer(from_fn(middleware::entry))
-                .layer(ClientIpSource::RightmostXForwardedFor.into_extension()),
+                .layer(from_fn(middleware::cors))
+                .layer(from_fn(middleware::auth))
+                .layer(from_fn_with_state(
+                    basic_config.request_limit as usize,
+                    middleware::processing_limit,
+                ))
+                .layer(ClientIpSource::RightmostXForwardedFor.into_extension())
+                // 需要在client ip之后才可获取
+                .layer(from_fn(middleware::rate_limit)),
         );
 
-    let basic_config = config::must_new_basic_config();
-
     info!("listening on http://{}", basic_config.listen);
-    let listener = tokio::net::TcpListener::bind(basic_config.listen)
+    let listener = tokio::net::TcpListener::bind(&basic_config.listen)
         .await
         .unwrap();
 
@@ -110,58 +134,28 @@ async fn run() {
     .unwrap();
 }
 
-fn load_fonts(dir: &str) {
-    let mut font_files = vec![];
-
-    let file_paths = [
-        format!(r#"{dir}/*.ttf"#),
-        format!(r#"{dir}/*.otf"#),
-        format!(r#"{dir}/**/*.ttf"#),
-        format!(r#"{dir}/**/*.otf"#),
-    ];
-    for file_path in file_paths.iter() {
-        for entry in glob(file_path)
-            .expect("Failed to read glob pattern")
-            .flatten()
-        {
-            font_files.push(entry)
-        }
-    }
-
-    let mut font_buffers = vec![];
-    for item in font_files.iter() {
-        if let Ok(buf) = fs::read(item) {
-            font_buffers.push(buf);
-        }
-    }
-    let arr: Vec<&[u8]> = font_buffers.iter().map(|item| item.as_slice()).collect();
-    charts_rs::get_or_try_init_fonts(Some(arr)).unwrap();
-}
 fn main() {
     init_logger();
+    let mut font_dirs = vec![];
     if let Ok(font_path) = env::var("CHARTS_FONT_PATH") {
-        info!(font_path, "loading fonts");
-        for item in font_path.split(',') {
-            load_fonts(item);
-        }
+        font_dirs.extend(font_path.split(',').map(|item| item.to_string()));
     }
-    let prefix = "CHARTS_THEME_";
-    for (name, value) in env::vars() {
-        if !name.starts_with(prefix) {
-            continue;
-        }
-        let name = name.substring(prefix.len(), name.len());
-        if name.is_empty() {
-            continue;
-        }
-        match serde_json::from_str::<charts_rs::Theme>(&value) {
-            Ok(theme) => {
-                charts_rs::add_theme(name, theme);
-            }
-            Err(err) => {
-                tracing::error!(error = err.to_string(), "add theme fail");
-            }
-        }
+    let font_config = config::must_new_font_config();
+    if !font_config.path.is_empty() {
+        font_dirs.push(font_config.path);
+    }
+    info!(font_dirs = font_dirs.join(","), "loading fonts");
+    font::load_fonts(&font_dirs);
+    let template_config = config::must_new_template_config();
+    if !template_config.path.is_empty() {
+        info!(path = template_config.path, "loading templates");
+        template::load_templates(&template_config.path);
+    }
+    theme::load_themes_from_env();
+    let theme_config = config::must_new_theme_config();
+    if !theme_config.path.is_empty() {
+        info!(path = theme_config.path, "loading themes");
+        theme::load_themes(&theme_config.path);
     }
     let families = charts_rs::get_font_families().unwrap();
     let themes = charts_rs::list_theme_name();
@@ -175,6 +169,10 @@ fn main() {
         .map(|v| v.parse::<usize>().unwrap_or(num_cpus))
         .unwrap_or(num_cpus)
         .max(1);
+    pool::init_render_pool();
+    middleware::init_api_keys();
+    middleware::init_rate_limit();
+    middleware::init_cors();
     info!(threads = cpus, "start charts server");
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -0,0 +1,115 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    exponential_buckets, register_histogram, register_histogram_vec, register_int_counter_vec,
+    register_int_gauge, Encoder, Histogram, HistogramVec, IntCounterVec, IntGauge, TextEncoder,
+};
+use std::time::Duration;
+
+static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "charts_http_requests_total",
+        "Number of http requests by route and status",
+        &["route", "status"]
+    )
+    .unwrap()
+});
+
+static HTTP_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "charts_http_errors_total",
+        "Number of http errors by category",
+        &["category"]
+    )
+    .unwrap()
+});
+
+static HTTP_PROCESSING: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "charts_http_processing",
+        "Number of http requests in processing"
+    )
+    .unwrap()
+});
+
+static RENDER_QUEUED: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "charts_render_queued",
+        "Number of render jobs waiting in the render pool"
+    )
+    .unwrap()
+});
+
+static RENDER_RUNNING: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "charts_render_running",
+        "Number of render jobs running in the render pool"
+    )
+    .unwrap()
+});
+
+static RENDER_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "charts_render_duration_seconds",
+        "Duration of chart rendering by chart type and format",
+        &["type", "format"],
+        exponential_buckets(0.005, 2.0, 12).unwrap()
+    )
+    .unwrap()
+});
+
+static RENDER_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "charts_render_size_bytes",
+        "Size of rendered chart by chart type and format",
+        &["type", "format"],
+        exponential_buckets(1024.0, 2.0, 12).unwrap()
+    )
+    .unwrap()
+});
+
+static PNG_QUANTIZE_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "charts_png_quantize_duration_seconds",
+        "Duration of png quantization",
+        exponential_buckets(0.001, 2.0, 12).unwrap()
+    )
+    .unwrap()
+});
+
+/// 记录http请求
+pub fn observe_http_request(route: &str, status: u16) {
+    HTTP_REQUESTS
+        .with_label_values(&[route, &status.to_string()])
+        .inc();
+}
+
+/// 记录http出错
+pub fn observe_http_error(category: &str) {
+    HTTP_ERRORS.with_label_values(&[category]).inc();
+}
+
+/// 记录图表生成的耗时与数据大小
+pub fn observe_render(chart_type: &str, format: &str, elapsed: Duration, size: usize) {
+    RENDER_DURATION
+        .with_label_values(&[chart_type, format])
+        .observe(elapsed.as_secs_f64());
+    RENDER_SIZE
+        .with_label_values(&[chart_type, format])
+        .observe(size as f64);
+}
+
+/// 记录png压缩的耗时
+pub fn observe_png_quantize(elapsed: Duration) {
+    PNG_QUANTIZE_DURATION.observe(elapsed.as_secs_f64());
+}
+
+/// 以prometheus的文本格式输出所有指标
+pub fn encode_metrics(processing: usize, render_queued: usize, render_running: usize) -> String {
+    HTTP_PROCESSING.set(processing as i64);
+    RENDER_QUEUED.set(render_queued as i64);
+    RENDER_RUNNING.set(render_running as i64);
+    let mut buf = vec![];
+    // 写入Vec不会失败
+    let _ = TextEncoder::new().encode(&prometheus::gather(), &mut buf);
+    String::from_utf8_lossy(&buf).to_string()
+}
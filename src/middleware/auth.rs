@@ -0,0 +1,335 @@
+use axum::extract::{MatchedPath, Query};
+use axum::http::{header, HeaderValue};
+use axum::response::IntoResponse;
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+use tracing::info;
+
+use super::bucket::TokenBucket;
+use super::buffer_body;
+use crate::config::must_new_auth_config;
+use crate::error::{HttpError, HttpResult};
+use crate::util::get_header_value;
+
+// 生成图表的路由，用于统计每天的生成数量
+static RENDER_ROUTES: [&str; 10] = [
+    "/api/charts",
+    "/api/charts/svg",
+    "/api/charts/png",
+    "/api/charts/webp",
+    "/api/charts/avif",
+    "/api/charts/jpeg",
+    "/api/charts/pdf",
+    "/api/charts/render",
+    "/api/charts/batch",
+    "/api/reports/pdf",
+];
+
+// 批量生成的路由，按图表的数量计算额度
+static BATCH_ROUTE: &str = "/api/charts/batch";
+
+// 认证通过的api key id，设置在响应的extensions中用于访问日志
+#[derive(Debug, Clone)]
+pub struct AuthKeyId(pub String);
+
+#[derive(Debug, Clone, Deserialize)]
+struct ApiKey {
+    // key的id，用于日志与统计，不会泄露key
+    id: String,
+    key: String,
+    // 每秒允许的请求数，未配置则使用auth.rate
+    rate: Option<f64>,
+    // 每天允许生成的图表数，未配置则使用auth.quota
+    quota: Option<u64>,
+}
+
+// api key的使用状态
+struct KeyState {
+    bucket: TokenBucket,
+    // 当天(UTC)的天数与已生成的图表数
+    day: i64,
+    renders: u64,
+}
+
+struct ApiKeys {
+    // 以key为索引
+    keys: HashMap<String, ApiKey>,
+    // 以id为索引
+    states: Mutex<HashMap<String, KeyState>>,
+}
+
+/// 从配置与文件中加载api key，文件支持json与yaml
+fn load_api_keys() -> HttpResult<Vec<ApiKey>> {
+    let config = must_new_auth_config();
+    let mut keys = vec![];
+    for item in config.keys.split(',') {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+        let Some((id, key)) = item.split_once(':') else {
+            return Err(HttpError::new_with_category(
+                &format!("Api key of {item} is invalid, it should be id:key"),
+                "auth",
+            ));
+        };
+        keys.push(ApiKey {
+            id: id.to_string(),
+            key: key.to_string(),
+            rate: None,
+            quota: None,
+        });
+    }
+    if !config.file.is_empty() {
+        let data = fs::read(&config.file)
+            .map_err(|err| HttpError::new_with_category(&err.to_string(), "auth"))?;
+        // yaml兼容json，因此统一以yaml解析
+        let items: Vec<ApiKey> = serde_yaml::from_slice(&data)
+            .map_err(|err| HttpError::new_with_category(&err.to_string(), "auth"))?;
+        keys.extend(items);
+    }
+    for item in keys.iter_mut() {
+        if item.id.is_empty() || item.key.is_empty() {
+            return Err(HttpError::new_with_category(
+                "Id and key of api key should not be empty",
+                "auth",
+            ));
+        }
+        item.rate = item.rate.or(Some(config.rate as f64));
+        item.quota = item.quota.or(Some(config.quota as u64));
+    }
+    Ok(keys)
+}
+
+/// 获取api key，若未启用认证则返回None
+fn get_api_keys() -> Option<&'static ApiKeys> {
+    static API_KEYS: OnceCell<Option<ApiKeys>> = OnceCell::new();
+    API_KEYS
+        .get_or_init(|| {
+            if !must_new_auth_config().enabled {
+                return None;
+            }
+            let keys = load_api_keys().unwrap_or_else(|err| panic!("{}", err.message));
+            info!(count = keys.len(), "load api keys success");
+            Some(ApiKeys {
+                keys: keys
+                    .into_iter()
+                    .map(|item| (item.key.clone(), item))
+                    .collect(),
+                states: Mutex::new(HashMap::new()),
+            })
+        })
+        .as_ref()
+}
+
+/// 初始化api key，启用认证但加载失败时直接panic
+pub fn init_api_keys() {
+    get_api_keys();
+}
+
+/// 从Bearer或query的key中获取api key
+fn get_request_key(req: &Request<Body>) -> Option<String> {
+    let authorization = get_header_value(req.headers(), header::AUTHORIZATION.as_str());
+    if let Some(key) = authorization.strip_prefix("Bearer ") {
+        return Some(key.trim().to_string());
+    }
+    Query::<HashMap<String, String>>::try_from_uri(req.uri())
+        .ok()
+        .and_then(|query| query.0.get("key").cloned())
+}
+
+fn new_unauthorized_response(message: &str) -> Response {
+    let mut resp =
+        HttpError::new_with_category_status(message, "unauthorized", 401).into_response();
+    resp.headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    resp
+}
+
+/// 获取批量生成的图表数量，读取body后返回重新生成的请求，
+/// 参数无法解析时按一个计算(由接口返回出错)
+async fn get_batch_count(req: Request<Body>) -> Result<(Request<Body>, u64), Response> {
+    let (req, bytes) = buffer_body(req).await?;
+    let count = serde_json::from_slice::<Vec<serde_json::Value>>(&bytes)
+        .map(|items| items.len() as u64)
+        .unwrap_or(1);
+    Ok((req, count.max(1)))
+}
+
+/// 校验api key的请求速率，并占用当天生成图表数量的额度(非生成图表的请求为0)
+fn acquire(api_key: &ApiKey, keys: &ApiKeys, renders: u64) -> Result<(), Box<Response>> {
+    let rate = api_key.rate.unwrap_or_default();
+    let quota = api_key.quota.unwrap_or_default();
+    let Ok(mut states) = keys.states.lock() else {
+        return Ok(());
+    };
+    let state = states
+        .entry(api_key.id.clone())
+        .or_insert_with(|| KeyState {
+            bucket: TokenBucket::new(rate.max(1.0)),
+            day: 0,
+            renders: 0,
+        });
+    if rate > 0.0 {
+        if let Err(wait) = state.bucket.take(rate, rate.max(1.0), 1.0) {
+            let mut resp = HttpError::new_with_category_status(
+                &format!("Too many requests of api key {}", api_key.id),
+                "too_many_requests",
+                429,
+            )
+            .into_response();
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+                resp.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+            return Err(Box::new(resp));
+        }
+    }
+    if renders > 0 && quota > 0 {
+        let day = Utc::now().timestamp().div_euclid(86400);
+        if state.day != day {
+            state.day = day;
+            state.renders = 0;
+        }
+        if state.renders + renders > quota {
+            return Err(Box::new(
+                HttpError::new_with_category_status(
+                    &format!(
+                        "Daily render quota {quota} of api key {} is exhausted",
+                        api_key.id
+                    ),
+                    "quota_exceeded",
+                    403,
+                )
+                .into_response(),
+            ));
+        }
+        state.renders += renders;
+    }
+    Ok(())
+}
+
+/// 生成失败时归还占用的额度
+fn release(api_key: &ApiKey, keys: &ApiKeys, renders: u64) {
+    if let Ok(mut states) = keys.states.lock() {
+        if let Some(state) = states.get_mut(&api_key.id) {
+            state.renders = state.renders.saturating_sub(renders);
+        }
+    }
+}
+
+/// api key认证，仅针对/api/*的接口，未指定或不存在的key返回401，
+/// 超出速率返回429，超出当天的生成额度返回403
+pub async fn auth(req: Request<Body>, next: Next) -> Response {
+    let Some(keys) = get_api_keys() else {
+        return next.run(req).await;
+    };
+    if !req.uri().path().starts_with("/api/") {
+        return next.run(req).await;
+    }
+    let Some(key) = get_request_key(&req) else {
+        return new_unauthorized_response("Api key is required");
+    };
+    let Some(api_key) = keys.keys.get(&key) else {
+        return new_unauthorized_response("Api key is invalid");
+    };
+    let key_id = AuthKeyId(api_key.id.clone());
+    let matched_path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let quota = api_key.quota.unwrap_or_default();
+    let (req, renders) = if !RENDER_ROUTES.contains(&matched_path.as_str()) {
+        (req, 0)
+    } else if matched_path == BATCH_ROUTE && quota > 0 {
+        match get_batch_count(req).await {
+            Ok(result) => result,
+            Err(resp) => return resp,
+        }
+    } else {
+        (req, 1)
+    };
+
+    let mut resp = match acquire(api_key, keys, renders) {
+        Ok(()) => {
+            let resp = next.run(req).await;
+            if renders > 0 && quota > 0 && resp.status().as_u16() >= 400 {
+                release(api_key, keys, renders);
+            }
+            resp
+        }
+        Err(resp) => *resp,
+    };
+    resp.extensions_mut().insert(key_id);
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{acquire, release, ApiKey, ApiKeys};
+    use axum::http::header;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    fn new_api_keys(rate: f64, quota: u64) -> (ApiKey, ApiKeys) {
+        let api_key = ApiKey {
+            id: "test".to_string(),
+            key: "secret".to_string(),
+            rate: Some(rate),
+            quota: Some(quota),
+        };
+        let keys = ApiKeys {
+            keys: HashMap::new(),
+            states: Mutex::new(HashMap::new()),
+        };
+        (api_key, keys)
+    }
+
+    #[test]
+    fn acquire_quota() {
+        let (api_key, keys) = new_api_keys(0.0, 3);
+        // 非生成图表的请求不占用额度
+        assert!(acquire(&api_key, &keys, 0).is_ok());
+        assert!(acquire(&api_key, &keys, 2).is_ok());
+        assert!(acquire(&api_key, &keys, 1).is_ok());
+
+        let resp = acquire(&api_key, &keys, 1).unwrap_err();
+        assert_eq!(403, resp.status().as_u16());
+
+        // 生成失败时归还额度
+        release(&api_key, &keys, 2);
+        assert!(acquire(&api_key, &keys, 2).is_ok());
+        assert!(acquire(&api_key, &keys, 1).is_err());
+    }
+
+    #[test]
+    fn acquire_batch_quota() {
+        let (api_key, keys) = new_api_keys(0.0, 3);
+        // 批量生成的数量超出剩余额度时整体拒绝，不占用额度
+        assert!(acquire(&api_key, &keys, 4).is_err());
+        assert!(acquire(&api_key, &keys, 3).is_ok());
+    }
+
+    #[test]
+    fn acquire_rate() {
+        let (api_key, keys) = new_api_keys(2.0, 0);
+        assert!(acquire(&api_key, &keys, 1).is_ok());
+        assert!(acquire(&api_key, &keys, 1).is_ok());
+        let resp = acquire(&api_key, &keys, 1).unwrap_err();
+        assert_eq!(429, resp.status().as_u16());
+        assert_eq!(
+            "1",
+            resp.headers()
+                .get(header::RETRY_AFTER)
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
+    }
+}
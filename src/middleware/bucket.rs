@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+// 令牌桶，按速率补充令牌，桶的容量为burst
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    /// 创建令牌桶，初始时为满
+    pub fn new(burst: f64) -> Self {
+        Self {
+            tokens: burst,
+            updated_at: Instant::now(),
+        }
+    }
+    /// 按速率补充令牌后获取指定数量的令牌，
+    /// 成功返回剩余的令牌数，失败则返回需要等待的时长
+    pub fn take(&mut self, rate: f64, burst: f64, cost: f64) -> Result<f64, Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        self.updated_at = now;
+        if self.tokens >= cost {
+            self.tokens -= cost;
+            return Ok(self.tokens);
+        }
+        Err(Duration::from_secs_f64((cost - self.tokens) / rate))
+    }
+    /// 令牌补满所需的时长
+    pub fn time_to_full(&self, rate: f64, burst: f64) -> Duration {
+        Duration::from_secs_f64(((burst - self.tokens) / rate).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenBucket;
+    use std::time::Duration;
+
+    #[test]
+    fn take_tokens() {
+        // 速率足够低，测试过程中补充的令牌可忽略
+        let rate = 0.001;
+        let mut bucket = TokenBucket::new(3.0);
+        assert_eq!(Duration::ZERO, bucket.time_to_full(rate, 3.0));
+
+        let tokens = bucket.take(rate, 3.0, 2.0).unwrap();
+        assert!((1.0..1.1).contains(&tokens));
+        let full = bucket.time_to_full(rate, 3.0).as_secs_f64();
+        assert!(full > 1900.0 && full <= 2000.0);
+
+        // 令牌不足时返回需要等待的时长，且不扣减令牌
+        let wait = bucket.take(rate, 3.0, 2.0).unwrap_err().as_secs_f64();
+        assert!(wait > 900.0 && wait <= 1000.0);
+        assert!(bucket.take(rate, 3.0, 1.0).is_ok());
+    }
+
+    #[test]
+    fn refill_tokens() {
+        let mut bucket = TokenBucket::new(1.0);
+        assert!(bucket.take(1000.0, 1.0, 1.0).is_ok());
+        std::thread::sleep(Duration::from_millis(10));
+        // 补充的令牌不超过桶的容量
+        let tokens = bucket.take(1000.0, 1.0, 1.0).unwrap();
+        assert_eq!(0.0, tokens);
+    }
+}
@@ -0,0 +1,137 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use once_cell::sync::OnceCell;
+use std::str::FromStr;
+use tower::{Layer, ServiceExt};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer, ExposeHeaders};
+
+use crate::config::must_new_cors_config;
+
+/// 判断来源是否匹配(不区分大小写)，支持一个通配符，如https://*.example.com，*则匹配所有
+fn match_origin(pattern: &str, origin: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let origin = origin.to_lowercase();
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            origin.len() >= prefix.len() + suffix.len()
+                && origin.starts_with(prefix)
+                && origin.ends_with(suffix)
+        }
+        None => pattern == origin,
+    }
+}
+
+/// 获取跨域的layer，若未配置来源则返回None
+fn get_cors_layer() -> Option<&'static CorsLayer> {
+    static CORS_LAYER: OnceCell<Option<CorsLayer>> = OnceCell::new();
+    CORS_LAYER
+        .get_or_init(|| {
+            let config = must_new_cors_config();
+            if config.origins.is_empty() {
+                return None;
+            }
+            let origins = config.origins.clone();
+            // 使用predicate而非通配符，响应返回请求的来源，因此可与credentials同时使用
+            let allow_origin = AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+                let origin = origin.to_str().unwrap_or_default();
+                origins.iter().any(|pattern| match_origin(pattern, origin))
+            });
+            let allow_methods = if config.methods.iter().any(|item| item == "*") {
+                AllowMethods::mirror_request()
+            } else {
+                AllowMethods::list(
+                    config
+                        .methods
+                        .iter()
+                        .filter_map(|item| Method::from_str(&item.to_uppercase()).ok()),
+                )
+            };
+            let allow_headers = if config.headers.iter().any(|item| item == "*") {
+                AllowHeaders::mirror_request()
+            } else {
+                AllowHeaders::list(
+                    config
+                        .headers
+                        .iter()
+                        .filter_map(|item| HeaderName::from_str(item).ok()),
+                )
+            };
+            let expose_headers = ExposeHeaders::list(
+                config
+                    .expose_headers
+                    .iter()
+                    .filter_map(|item| HeaderName::from_str(item).ok()),
+            );
+            Some(
+                CorsLayer::new()
+                    .allow_origin(allow_origin)
+                    .allow_methods(allow_methods)
+                    .allow_headers(allow_headers)
+                    .expose_headers(expose_headers)
+                    .max_age(config.max_age)
+                    .allow_credentials(config.credentials),
+            )
+        })
+        .as_ref()
+}
+
+/// 初始化跨域配置，来源为*且允许携带凭证时直接panic
+pub fn init_cors() {
+    get_cors_layer();
+}
+
+/// 跨域处理，仅针对/api/*的接口，预检请求直接响应，
+/// 因此需要在认证与限流之前
+pub async fn cors(req: Request<Body>, next: Next) -> Response {
+    let Some(layer) = get_cors_layer() else {
+        return next.run(req).await;
+    };
+    if !req.uri().path().starts_with("/api/") {
+        return next.run(req).await;
+    }
+    match layer.layer(next).oneshot(req).await {
+        Ok(resp) => resp,
+        Err(err) => match err {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::match_origin;
+
+    #[test]
+    fn match_exact_origin() {
+        assert!(match_origin("https://example.com", "https://example.com"));
+        assert!(match_origin("https://Example.com", "HTTPS://example.COM"));
+        assert!(!match_origin(
+            "https://example.com",
+            "https://example.com.cn"
+        ));
+        assert!(!match_origin("https://example.com", "http://example.com"));
+    }
+
+    #[test]
+    fn match_wildcard_origin() {
+        assert!(match_origin("*", "https://example.com"));
+        assert!(match_origin(
+            "https://*.example.com",
+            "https://api.example.com"
+        ));
+        assert!(match_origin(
+            "https://*.Example.com",
+            "https://API.example.COM"
+        ));
+        assert!(!match_origin(
+            "https://*.example.com",
+            "https://example.com"
+        ));
+        assert!(!match_origin(
+            "https://*.example.com",
+            "https://api.example.org"
+        ));
+        assert!(!match_origin(
+            "https://*.example.com",
+            "http://api.example.com"
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+use axum::extract::State;
+use axum::http::{header, HeaderValue};
+use axum::response::IntoResponse;
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::HttpError;
+
+static PROCESSING: AtomicUsize = AtomicUsize::new(0);
+
+/// 获取当前处理中的请求数
+pub fn get_processing() -> usize {
+    PROCESSING.load(Ordering::Relaxed)
+}
+
+// 请求结束时(包括被取消)减少处理中的请求数
+struct ProcessingGuard;
+
+impl Drop for ProcessingGuard {
+    fn drop(&mut self) {
+        PROCESSING.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 限制同时处理的请求数，超出时返回429
+pub async fn processing_limit(
+    State(limit): State<usize>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let _guard = ProcessingGuard;
+    let processing = PROCESSING.fetch_add(1, Ordering::Relaxed) + 1;
+    if processing > limit {
+        let mut resp = HttpError::new_with_category_status(
+            &format!("Too many requests, processing: {processing}, limit: {limit}"),
+            "too_many_requests",
+            429,
+        )
+        .into_response();
+        resp.headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+        return resp;
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_processing, processing_limit};
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::middleware::from_fn_with_state;
+    use axum::routing::get;
+    use axum::Router;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn limit_processing() {
+        let notify = Arc::new(Notify::new());
+        let value = notify.clone();
+        let app = Router::new()
+            .route(
+                "/",
+                get(move || async move {
+                    value.notified().await;
+                    "ok"
+                }),
+            )
+            .layer(from_fn_with_state(1, processing_limit));
+        let new_request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+
+        let first = tokio::spawn(app.clone().oneshot(new_request()));
+        while get_processing() == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        // 超出限制时直接返回429
+        let resp = app.clone().oneshot(new_request()).await.unwrap();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status());
+        assert_eq!("1", resp.headers()[header::RETRY_AFTER]);
+        assert_eq!(1, get_processing());
+
+        notify.notify_one();
+        let resp = first.await.unwrap().unwrap();
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(0, get_processing());
+
+        // 请求被取消时同样减少处理中的请求数
+        let pending = tokio::spawn(app.clone().oneshot(new_request()));
+        while get_processing() == 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        pending.abort();
+        let _ = pending.await;
+        assert_eq!(0, get_processing());
+    }
+}
@@ -1,12 +1,48 @@
+use axum::body::Bytes;
 use axum::http::{header, header::HeaderName, HeaderMap, HeaderValue};
+use axum::response::IntoResponse;
 use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use crate::config::must_new_basic_config;
+use crate::error::HttpError;
+
+mod auth;
+mod bucket;
+mod cors;
+mod limit;
+mod rate;
 mod stats;
 
+pub use auth::{auth, init_api_keys, AuthKeyId};
+pub use cors::{cors, init_cors};
+pub use limit::{get_processing, processing_limit};
+pub use rate::{init_rate_limit, rate_limit};
 pub use stats::access_log;
 
+/// 读取请求的body(不超过basic.maxBodySize)，返回重新生成的请求与body，
+/// 用于需要根据body计算的中间件
+async fn buffer_body(req: Request<Body>) -> Result<(Request<Body>, Bytes), Response> {
+    let (parts, body) = req.into_parts();
+    let limit = must_new_basic_config().max_body_size as usize;
+    let bytes = Limited::new(body, limit)
+        .collect()
+        .await
+        .map_err(|err| {
+            let status = if err.is::<LengthLimitError>() {
+                413
+            } else {
+                400
+            };
+            HttpError::new_with_category_status(&err.to_string(), "body_to_bytes", status)
+                .into_response()
+        })?
+        .to_bytes();
+    Ok((Request::from_parts(parts, Body::from(bytes.clone())), bytes))
+}
+
 /// 插入HTTP头
 fn insert_header(headers: &mut HeaderMap<HeaderValue>, values: HashMap<String, String>) {
     // 如果失败则不设置
@@ -0,0 +1,398 @@
+use axum::extract::{ConnectInfo, FromRequestParts, Query};
+use axum::http::{header, request::Parts, HeaderMap, HeaderName, HeaderValue, Method};
+use axum::response::IntoResponse;
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use axum_client_ip::ClientIp;
+use ipnet::IpNet;
+use lru::LruCache;
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::bucket::TokenBucket;
+use super::buffer_body;
+use crate::codec::{decode_options, Encoding};
+use crate::config::must_new_rate_limit_config;
+use crate::controller::negotiate_format_name;
+use crate::error::HttpError;
+use crate::ingest::{parse_chart_params, DataFormat};
+
+// 保存令牌桶的ip数量，超出时淘汰最久未使用的
+static MAX_CLIENTS: usize = 100_000;
+// 图表未指定宽高时的默认值
+static DEFAULT_WIDTH: f64 = 600.0;
+static DEFAULT_HEIGHT: f64 = 400.0;
+// 批量生成的路由，消耗为各图表消耗之和
+static BATCH_PATH: &str = "/api/charts/batch";
+
+static RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+static RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+static RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    allowlist: Vec<IpNet>,
+    weights: HashMap<String, f64>,
+    pixels: f64,
+    buckets: Mutex<LruCache<IpAddr, TokenBucket>>,
+}
+
+/// 获取限流器，若未配置速率则返回None
+fn get_rate_limiter() -> Option<&'static RateLimiter> {
+    static RATE_LIMITER: OnceCell<Option<RateLimiter>> = OnceCell::new();
+    RATE_LIMITER
+        .get_or_init(|| {
+            let config = must_new_rate_limit_config();
+            if config.rate <= 0 {
+                return None;
+            }
+            let allowlist = config
+                .allowlist
+                .iter()
+                .map(|item| {
+                    // 单个ip则转换为对应的网段
+                    item.parse::<IpNet>()
+                        .or_else(|_| item.parse::<IpAddr>().map(IpNet::from))
+                        .unwrap_or_else(|_| panic!("Allowlist {item} is invalid"))
+                })
+                .collect();
+            Some(RateLimiter {
+                rate: config.rate as f64,
+                burst: config.burst.max(1) as f64,
+                allowlist,
+                weights: config
+                    .weights
+                    .into_iter()
+                    .map(|(format, weight)| (format, weight as f64))
+                    .collect(),
+                pixels: config.pixels as f64,
+                buckets: Mutex::new(LruCache::new(NonZeroUsize::new(MAX_CLIENTS).unwrap())),
+            })
+        })
+        .as_ref()
+}
+
+/// 初始化限流器，allowlist配置错误时直接panic
+pub fn init_rate_limit() {
+    get_rate_limiter();
+}
+
+/// 获取客户端ip，若X-Forwarded-For中无则使用连接的地址
+async fn get_client_ip(parts: &mut Parts) -> Option<IpAddr> {
+    if let Ok(ClientIp(ip)) = ClientIp::from_request_parts(parts, &()).await {
+        return Some(ip);
+    }
+    parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+/// 根据路由获取生成图表的输出格式(小写，与接口的处理一致)，非生成图表的请求返回None
+fn get_render_format(
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+) -> Option<String> {
+    if path == "/api/charts" && method == Method::GET {
+        return Some(
+            query
+                .get("format")
+                .map(|format| format.to_lowercase())
+                .unwrap_or("svg".to_string()),
+        );
+    }
+    // 与接口一致根据accept选择格式，无匹配的格式返回406，按普通请求计算
+    if path == "/api/charts/render" {
+        let accept = headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        return negotiate_format_name(accept).map(|format| format.to_string());
+    }
+    if path == "/api/reports/pdf" {
+        return Some("pdf".to_string());
+    }
+    if let Some(file) = path.strip_prefix("/c/") {
+        return file.rsplit_once('.').map(|(_, ext)| ext.to_lowercase());
+    }
+    if method == Method::POST {
+        return path
+            .strip_prefix("/api/charts/")
+            .map(|item| item.to_string());
+    }
+    None
+}
+
+/// 获取图表的像素数(宽x高x缩放比例的平方)
+fn get_chart_pixels(value: &serde_json::Value, scale: Option<f64>) -> f64 {
+    let get = |key: &str| value.get(key).and_then(|v| v.as_f64());
+    let width = get("width").unwrap_or(DEFAULT_WIDTH);
+    let height = get("height").unwrap_or(DEFAULT_HEIGHT);
+    let scale = scale.or(get("scale")).or(get("dpr")).unwrap_or(1.0);
+    width * height * scale * scale
+}
+
+/// 计算单个图表消耗的令牌数，输出格式的权重乘以画布的单位数，
+/// 未配置权重的格式消耗1个
+fn get_chart_cost(
+    limiter: &RateLimiter,
+    format: &str,
+    value: Option<&serde_json::Value>,
+    scale: Option<f64>,
+) -> f64 {
+    let Some(weight) = limiter.weights.get(format).copied() else {
+        return 1.0;
+    };
+    if limiter.pixels <= 0.0 {
+        return weight;
+    }
+    let units = value
+        .map(|value| (get_chart_pixels(value, scale) / limiter.pixels).ceil())
+        .unwrap_or(1.0)
+        .max(1.0);
+    weight * units
+}
+
+/// 计算批量生成消耗的令牌数，为各图表的消耗之和
+async fn get_batch_cost(
+    limiter: &RateLimiter,
+    req: Request<Body>,
+) -> Result<(Request<Body>, f64), Response> {
+    let (req, bytes) = buffer_body(req).await?;
+    // 参数无法解析时由接口返回出错，按一个图表计算
+    let items: Vec<serde_json::Value> = serde_json::from_slice(&bytes).unwrap_or_default();
+    let cost: f64 = items
+        .iter()
+        .map(|item| {
+            let format = item
+                .get("format")
+                .and_then(|v| v.as_str())
+                .unwrap_or("svg")
+                .to_lowercase();
+            get_chart_cost(limiter, &format, Some(item), None)
+        })
+        .sum();
+    Ok((req, cost.max(1.0)))
+}
+
+/// 计算请求消耗的令牌数，输出格式的权重乘以画布的单位数，
+/// 若需要按画布加权则读取body，返回重新生成的请求
+async fn get_request_cost(
+    limiter: &RateLimiter,
+    req: Request<Body>,
+) -> Result<(Request<Body>, f64), Response> {
+    let query = Query::<HashMap<String, String>>::try_from_uri(req.uri())
+        .map(|query| query.0)
+        .unwrap_or_default();
+    let path = req.uri().path().to_string();
+    if path == BATCH_PATH && req.method() == Method::POST {
+        return get_batch_cost(limiter, req).await;
+    }
+    let Some(format) = get_render_format(req.method(), &path, req.headers(), &query) else {
+        return Ok((req, 1.0));
+    };
+    // 分享链接的参数需校验签名后才可获取，因此仅按格式计算，
+    // 不按画布加权时也无需读取body
+    if limiter.pixels <= 0.0 || path.starts_with("/c/") || !limiter.weights.contains_key(&format) {
+        return Ok((req, get_chart_cost(limiter, &format, None, None)));
+    }
+    let scale = query
+        .get("scale")
+        .or(query.get("dpr"))
+        .and_then(|v| v.parse::<f64>().ok());
+    let (req, value) = if req.method() == Method::GET {
+        let enc = query.get("enc").and_then(|v| Encoding::from_name(v));
+        let value = query
+            .get("opts")
+            .and_then(|opts| decode_options(opts, enc).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok());
+        (req, value)
+    } else {
+        let (req, bytes) = buffer_body(req).await?;
+        let value = parse_chart_params(&bytes, DataFormat::from(req.headers())).ok();
+        (req, value)
+    };
+    Ok((req, get_chart_cost(limiter, &format, value.as_ref(), scale)))
+}
+
+fn set_rate_limit_headers(headers: &mut HeaderMap, limit: f64, remaining: f64, reset: Duration) {
+    let values = [
+        (&RATELIMIT_LIMIT, limit),
+        (&RATELIMIT_REMAINING, remaining.floor()),
+        (&RATELIMIT_RESET, reset.as_secs_f64().ceil()),
+    ];
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::from_str(&(value as u64).to_string()) {
+            headers.insert(name.clone(), value);
+        }
+    }
+}
+
+/// 按客户端ip限流(令牌桶)，仅针对/api/*与分享链接，allowlist中的ip不限制，
+/// 超出时返回429，响应均设置RateLimit-*
+pub async fn rate_limit(req: Request<Body>, next: Next) -> Response {
+    let Some(limiter) = get_rate_limiter() else {
+        return next.run(req).await;
+    };
+    let path = req.uri().path();
+    if !path.starts_with("/api/") && !path.starts_with("/c/") {
+        return next.run(req).await;
+    }
+    let (mut parts, body) = req.into_parts();
+    let Some(ip) = get_client_ip(&mut parts).await else {
+        return next.run(Request::from_parts(parts, body)).await;
+    };
+    if limiter.allowlist.iter().any(|item| item.contains(&ip)) {
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+    let (req, cost) = match get_request_cost(limiter, Request::from_parts(parts, body)).await {
+        Ok(result) => result,
+        Err(resp) => return resp,
+    };
+    // 消耗超出容量的请求按容量计算，避免永远无法执行
+    let cost = cost.min(limiter.burst);
+    let result = if let Ok(mut buckets) = limiter.buckets.lock() {
+        let bucket = buckets.get_or_insert_mut(ip, || TokenBucket::new(limiter.burst));
+        let result = bucket.take(limiter.rate, limiter.burst, cost);
+        let reset = bucket.time_to_full(limiter.rate, limiter.burst);
+        Some((result, reset))
+    } else {
+        None
+    };
+    match result {
+        Some((Ok(remaining), reset)) => {
+            let mut resp = next.run(req).await;
+            set_rate_limit_headers(resp.headers_mut(), limiter.burst, remaining, reset);
+            resp
+        }
+        Some((Err(wait), _)) => {
+            let mut resp = HttpError::new_with_category_status(
+                &format!("Too many requests of {ip}, please retry later"),
+                "too_many_requests",
+                429,
+            )
+            .into_response();
+            set_rate_limit_headers(resp.headers_mut(), limiter.burst, 0.0, wait);
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+                resp.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+            resp
+        }
+        None => next.run(req).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_chart_cost, get_render_format, RateLimiter};
+    use axum::http::{header, HeaderMap, HeaderValue, Method};
+    use lru::LruCache;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::num::NonZeroUsize;
+    use std::sync::Mutex;
+
+    fn new_limiter(pixels: f64) -> RateLimiter {
+        RateLimiter {
+            rate: 10.0,
+            burst: 100.0,
+            allowlist: vec![],
+            weights: [("svg", 1.0), ("png", 2.0), ("avif", 10.0)]
+                .iter()
+                .map(|(format, weight)| (format.to_string(), *weight))
+                .collect(),
+            pixels,
+            buckets: Mutex::new(LruCache::new(NonZeroUsize::new(10).unwrap())),
+        }
+    }
+
+    #[test]
+    fn render_format_is_lowercase() {
+        let headers = HeaderMap::new();
+        let query: HashMap<String, String> = [("format".to_string(), "AVIF".to_string())].into();
+        assert_eq!(
+            Some("avif".to_string()),
+            get_render_format(&Method::GET, "/api/charts", &headers, &query)
+        );
+        assert_eq!(
+            Some("svg".to_string()),
+            get_render_format(&Method::GET, "/api/charts", &headers, &HashMap::new())
+        );
+        assert_eq!(
+            Some("avif".to_string()),
+            get_render_format(&Method::GET, "/c/abc.AVIF", &headers, &HashMap::new())
+        );
+        assert_eq!(
+            Some("png".to_string()),
+            get_render_format(&Method::POST, "/api/charts/png", &headers, &HashMap::new())
+        );
+        assert_eq!(
+            None,
+            get_render_format(&Method::GET, "/api/templates", &headers, &HashMap::new())
+        );
+    }
+
+    #[test]
+    fn render_format_from_accept() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static("image/avif"));
+        assert_eq!(
+            Some("avif".to_string()),
+            get_render_format(
+                &Method::POST,
+                "/api/charts/render",
+                &headers,
+                &HashMap::new()
+            )
+        );
+        headers.insert(header::ACCEPT, HeaderValue::from_static("text/html"));
+        assert_eq!(
+            None,
+            get_render_format(
+                &Method::POST,
+                "/api/charts/render",
+                &headers,
+                &HashMap::new()
+            )
+        );
+    }
+
+    #[test]
+    fn chart_cost_by_format() {
+        let limiter = new_limiter(0.0);
+        assert_eq!(10.0, get_chart_cost(&limiter, "avif", None, None));
+        assert_eq!(2.0, get_chart_cost(&limiter, "png", None, None));
+        // 未配置权重的格式消耗1个
+        assert_eq!(1.0, get_chart_cost(&limiter, "jpeg", None, None));
+        // 未按画布加权时忽略画布大小
+        let value = json!({"width": 2000, "height": 2000});
+        assert_eq!(2.0, get_chart_cost(&limiter, "png", Some(&value), None));
+    }
+
+    #[test]
+    fn chart_cost_by_pixels() {
+        // 每240000像素(默认画布600x400)为一个单位
+        let limiter = new_limiter(240_000.0);
+        assert_eq!(2.0, get_chart_cost(&limiter, "png", None, None));
+        assert_eq!(2.0, get_chart_cost(&limiter, "png", Some(&json!({})), None));
+        let value = json!({"width": 1200, "height": 400});
+        assert_eq!(4.0, get_chart_cost(&limiter, "png", Some(&value), None));
+        // 缩放比例按平方计算，query的缩放比例优先
+        let value = json!({"scale": 2});
+        assert_eq!(8.0, get_chart_cost(&limiter, "png", Some(&value), None));
+        assert_eq!(
+            18.0,
+            get_chart_cost(&limiter, "png", Some(&value), Some(3.0))
+        );
+        // 小于一个单位的按一个计算
+        let value = json!({"width": 100, "height": 100});
+        assert_eq!(10.0, get_chart_cost(&limiter, "avif", Some(&value), None));
+    }
+}
@@ -1,14 +1,36 @@
+use axum::extract::MatchedPath;
 use axum::{body::Body, http::Request, middleware::Next, response::Response};
 use chrono::Utc;
 use tracing::info;
 use urlencoding::decode;
 
+use super::AuthKeyId;
+use crate::metrics::observe_http_request;
 use crate::util::get_header_value;
 
+/// 隐藏query中的api key，避免输出至日志
+fn redact_key(uri: &str) -> String {
+    let Some((path, query)) = uri.split_once('?') else {
+        return uri.to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|item| {
+            if item.starts_with("key=") {
+                "key=***"
+            } else {
+                item
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{path}?{query}")
+}
+
 pub async fn access_log(req: Request<Body>, next: Next) -> Response {
     let start_at = Utc::now().timestamp_millis();
 
-    let mut uri = req.uri().to_string();
+    let mut uri = redact_key(&req.uri().to_string());
     // decode成功则替换
     if let Ok(result) = decode(&uri) {
         uri = result.to_string()
@@ -25,15 +47,28 @@ pub async fn access_log(req: Request<Body>, next: Next) -> Response {
         .trim()
         .to_string();
 
+    // 以匹配的路由作为统计的label，未匹配的(静态文件)统一处理
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|item| item.as_str().to_string())
+        .unwrap_or_else(|| "fallback".to_string());
+
     let resp = next.run(req).await;
 
     let status = resp.status().as_u16();
+    let key_id = resp
+        .extensions()
+        .get::<AuthKeyId>()
+        .map(|item| item.0.clone())
+        .unwrap_or_default();
+    observe_http_request(&route, status);
 
     let cost = Utc::now().timestamp_millis() - start_at;
 
     info!(
         category = "access",
-        ip, x_forwarded_for, referrer, method, uri, status, cost,
+        ip, x_forwarded_for, referrer, key_id, method, uri, status, cost,
     );
 
     resp
@@ -0,0 +1,58 @@
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use svg2pdf::usvg::{fontdb, Options, Tree};
+use svg2pdf::{ConversionOptions, PageOptions};
+use tracing::error;
+
+use crate::error::{HttpError, HttpResult};
+use crate::font::list_fonts;
+
+// 与charts-rs使用相同的字体，首次使用时初始化
+static FONT_DB: Lazy<Arc<fontdb::Database>> = Lazy::new(|| {
+    let mut db = fontdb::Database::new();
+    db.load_font_data(charts_rs::DEFAULT_FONT_DATA.to_vec());
+    for font in list_fonts().iter() {
+        if !font.loaded || font.file.is_empty() {
+            continue;
+        }
+        if let Err(err) = db.load_font_file(&font.file) {
+            error!(
+                file = font.file,
+                error = err.to_string(),
+                "load pdf font fail"
+            );
+        }
+    }
+    // 未匹配的字体使用默认字体
+    db.set_sans_serif_family(charts_rs::DEFAULT_FONT_FAMILY);
+    db.set_serif_family(charts_rs::DEFAULT_FONT_FAMILY);
+    Arc::new(db)
+});
+
+fn new_pdf_error(err: impl ToString) -> HttpError {
+    HttpError::new_with_category(&err.to_string(), "pdf")
+}
+
+/// 解析svg，文字使用已加载的字体排版
+pub fn parse_svg(svg: &str) -> HttpResult<Tree> {
+    let options = Options {
+        font_family: charts_rs::DEFAULT_FONT_FAMILY.to_string(),
+        fontdb: FONT_DB.clone(),
+        ..Default::default()
+    };
+    Tree::from_str(svg, &options).map_err(new_pdf_error)
+}
+
+/// 将svg解析后的tree转换为pdf的chunk(以xobject的形式)，返回chunk与xobject的引用，
+/// 用到的字体以子集的形式嵌入
+pub fn tree_to_chunk(tree: &Tree) -> HttpResult<(pdf_writer::Chunk, pdf_writer::Ref)> {
+    svg2pdf::to_chunk(tree, ConversionOptions::default()).map_err(new_pdf_error)
+}
+
+/// 将svg转换为单页的pdf，页面大小与图表一致(1px对应1pt)，
+/// 字体以子集的形式嵌入，因此文字可选择与复制
+pub fn svg_to_pdf(svg: &str) -> HttpResult<Vec<u8>> {
+    let tree = parse_svg(svg)?;
+    svg2pdf::to_pdf(&tree, ConversionOptions::default(), PageOptions::default())
+        .map_err(new_pdf_error)
+}
@@ -0,0 +1,258 @@
+use once_cell::sync::OnceCell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tracing::{error, info};
+
+use crate::config::must_new_render_config;
+use crate::error::{HttpError, HttpResult};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// 队列中等待的任务数
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+// 正在执行的任务数
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// 图表生成的线程池，与tokio的线程分开，
+/// 避免耗时的图表生成(如avif)影响其它请求
+struct RenderPool {
+    sender: SyncSender<Job>,
+    threads: usize,
+    queue_size: usize,
+}
+
+fn get_render_pool() -> &'static RenderPool {
+    static RENDER_POOL: OnceCell<RenderPool> = OnceCell::new();
+    RENDER_POOL.get_or_init(|| {
+        let render_config = must_new_render_config();
+        let (sender, receiver) = sync_channel::<Job>(render_config.queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..render_config.threads {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("render-{index}"))
+                .spawn(move || run_worker(receiver))
+                .expect("Failed to spawn render thread");
+        }
+        info!(
+            threads = render_config.threads,
+            queue_size = render_config.queue_size,
+            "init render pool"
+        );
+        RenderPool {
+            sender,
+            threads: render_config.threads,
+            queue_size: render_config.queue_size,
+        }
+    })
+}
+
+/// 初始化线程池，启动时调用以便尽早校验配置
+pub fn init_render_pool() {
+    get_render_pool();
+}
+
+fn run_worker(receiver: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => break,
+        };
+        let Ok(job) = job else {
+            break;
+        };
+        QUEUED.fetch_sub(1, Ordering::Relaxed);
+        RUNNING.fetch_add(1, Ordering::Relaxed);
+        // 单个任务panic不影响线程继续处理
+        if catch_unwind(AssertUnwindSafe(job)).is_err() {
+            error!("render job panicked");
+        }
+        RUNNING.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RenderPoolStats {
+    pub threads: usize,
+    pub queue_size: usize,
+    pub queued: usize,
+    pub running: usize,
+}
+
+/// 获取线程池的状态
+pub fn get_render_pool_stats() -> RenderPoolStats {
+    let pool = get_render_pool();
+    RenderPoolStats {
+        threads: pool.threads,
+        queue_size: pool.queue_size,
+        queued: QUEUED.load(Ordering::Relaxed),
+        running: RUNNING.load(Ordering::Relaxed),
+    }
+}
+
+/// 在线程池中执行图表生成，队列已满则直接返回503，
+/// 超时(包括在队列中等待的时间)则返回408，此时若任务未开始则不再执行，
+/// 已开始执行的任务其结果会被丢弃
+pub async fn spawn_render<T, F>(format: &str, timeout: Duration, f: F) -> HttpResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> HttpResult<T> + Send + 'static,
+{
+    let start_at = Instant::now();
+    let (tx, rx) = oneshot::channel();
+    let job: Job = Box::new(move || {
+        // 请求已超时或取消(如客户端断开)
+        if tx.is_closed() {
+            return;
+        }
+        let _ = tx.send(f());
+    });
+    QUEUED.fetch_add(1, Ordering::Relaxed);
+    if let Err(err) = get_render_pool().sender.try_send(job) {
+        QUEUED.fetch_sub(1, Ordering::Relaxed);
+        let err = match err {
+            TrySendError::Full(_) => HttpError::new_with_category_status(
+                "Render queue is full, please retry later",
+                "render_queue_full",
+                503,
+            ),
+            // 工作线程均已退出，重试也无法恢复
+            TrySendError::Disconnected(_) => HttpError::new_with_category_status(
+                "Render pool is closed",
+                "render_pool_closed",
+                500,
+            ),
+        };
+        return Err(err);
+    }
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(result) => result.map_err(|_| {
+            HttpError::new_with_category_status("Render job is aborted", "render_aborted", 500)
+        })?,
+        Err(_) => Err(HttpError::new_with_category_status(
+            &format!(
+                "Render {format} timed out after {}ms",
+                start_at.elapsed().as_millis()
+            ),
+            "render_timeout",
+            408,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_render_pool_stats, spawn_render};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tokio::sync::Mutex;
+
+    // 线程池为全局共享，测试需要依次执行
+    static POOL_LOCK: Mutex<()> = Mutex::const_new(());
+
+    /// 等待线程池空闲后阻塞所有线程，并在队列中添加指定数量的任务，
+    /// 返回用于放行的开关
+    async fn block_render_pool(
+        queued: usize,
+    ) -> (Arc<AtomicBool>, Vec<tokio::task::JoinHandle<()>>) {
+        let stats = get_render_pool_stats();
+        wait_render_pool(0, 0).await;
+        let gate = Arc::new(AtomicBool::new(false));
+        let mut handles = vec![];
+        // 先占用所有线程再添加队列中的任务，避免线程未取出任务时队列已满
+        for (count, running, queued) in [
+            (stats.threads, stats.threads, 0),
+            (queued, stats.threads, queued),
+        ] {
+            for _ in 0..count {
+                let gate = gate.clone();
+                handles.push(tokio::spawn(async move {
+                    let result = spawn_render("svg", Duration::from_secs(60), move || {
+                        while !gate.load(Ordering::Relaxed) {
+                            std::thread::sleep(Duration::from_millis(1));
+                        }
+                        Ok(())
+                    })
+                    .await;
+                    assert!(result.is_ok());
+                }));
+            }
+            wait_render_pool(running, queued).await;
+        }
+        (gate, handles)
+    }
+
+    async fn wait_render_pool(running: usize, queued: usize) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let stats = get_render_pool_stats();
+            if stats.running == running && stats.queued == queued {
+                break;
+            }
+            assert!(Instant::now() < deadline, "render pool is busy");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn render_queue_full() {
+        let _lock = POOL_LOCK.lock().await;
+        let (gate, handles) = block_render_pool(get_render_pool_stats().queue_size).await;
+
+        let err = spawn_render("png", Duration::from_secs(1), || Ok(()))
+            .await
+            .unwrap_err();
+        assert_eq!(503, err.status);
+        assert_eq!("render_queue_full", err.category);
+
+        gate.store(true, Ordering::Relaxed);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(
+            2,
+            spawn_render("png", Duration::from_secs(1), || Ok(1 + 1))
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn render_timeout() {
+        let _lock = POOL_LOCK.lock().await;
+        let err = spawn_render("avif", Duration::from_millis(20), || {
+            std::thread::sleep(Duration::from_millis(200));
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(408, err.status);
+        assert_eq!("render_timeout", err.category);
+
+        // 在队列中等待时已超时的任务不再执行
+        let (gate, handles) = block_render_pool(0).await;
+        let executed = Arc::new(AtomicBool::new(false));
+        let value = executed.clone();
+        let err = spawn_render("avif", Duration::ZERO, move || {
+            value.store(true, Ordering::Relaxed);
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(408, err.status);
+        gate.store(true, Ordering::Relaxed);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert!(spawn_render("svg", Duration::from_secs(1), || Ok(()))
+            .await
+            .is_ok());
+        assert!(!executed.load(Ordering::Relaxed));
+    }
+}
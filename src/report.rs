@@ -0,0 +1,471 @@
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref};
+use serde::Deserialize;
+use std::collections::HashMap;
+use svg2pdf::usvg::Tree;
+
+use crate::error::{HttpError, HttpResult};
+use crate::pdf::{parse_svg, tree_to_chunk};
+
+// 图表之间的间隔
+static GAP: f32 = 12.0;
+// 文本字体大小的上限(pt)
+static MAX_TEXT_FONT_SIZE: f32 = 200.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReportMargin {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Default for ReportMargin {
+    fn default() -> Self {
+        ReportMargin {
+            left: 36.0,
+            top: 36.0,
+            right: 36.0,
+            bottom: 36.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReportItem {
+    // 图表参数，与生成图表的参数一致
+    Chart {
+        options: serde_json::Value,
+    },
+    // 文本，占满整行，超出宽度自动换行
+    Text {
+        text: String,
+        font_size: Option<f32>,
+        font_family: Option<String>,
+        font_color: Option<String>,
+        bold: Option<bool>,
+    },
+    // 分页
+    PageBreak,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReportDocument {
+    // 页面大小：A3、A4、A5、Letter或Legal，默认为A4
+    pub page_size: String,
+    // 是否横向
+    pub landscape: bool,
+    // 自定义页面的宽高(pt)，优先于page_size
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub margin: ReportMargin,
+    // 标题，在首页顶部展示
+    pub title: Option<String>,
+    // 每行展示的图表数量
+    pub columns: usize,
+    pub items: Vec<ReportItem>,
+}
+
+impl Default for ReportDocument {
+    fn default() -> Self {
+        ReportDocument {
+            page_size: "A4".to_string(),
+            landscape: false,
+            width: None,
+            height: None,
+            margin: ReportMargin::default(),
+            title: None,
+            columns: 2,
+            items: vec![],
+        }
+    }
+}
+
+impl ReportDocument {
+    /// 生成前校验报表内容，图表数量不能超过max_charts，
+    /// 文本的字体大小需大于0且不超过上限，否则返回400
+    pub fn check(&self, max_charts: usize) -> HttpResult<()> {
+        let charts = self
+            .items
+            .iter()
+            .filter(|item| matches!(item, ReportItem::Chart { .. }))
+            .count();
+        if charts > max_charts {
+            return Err(HttpError::new_with_category(
+                &format!("Report chart count {charts} exceeds the limit {max_charts}"),
+                "report_too_large",
+            ));
+        }
+        for (index, item) in self.items.iter().enumerate() {
+            let ReportItem::Text {
+                font_size: Some(font_size),
+                ..
+            } = item
+            else {
+                continue;
+            };
+            if !(*font_size > 0.0 && *font_size <= MAX_TEXT_FONT_SIZE) {
+                return Err(HttpError::new_with_category(
+                    &format!(
+                        "Font size {font_size} of item {index} is invalid, it should be in (0, {MAX_TEXT_FONT_SIZE}]"
+                    ),
+                    "report",
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// 获取页面的宽高(pt)
+    fn get_page_size(&self) -> HttpResult<(f32, f32)> {
+        let (width, height) = match self.page_size.to_lowercase().as_str() {
+            "a3" => (842.0, 1191.0),
+            "a5" => (420.0, 595.0),
+            "letter" => (612.0, 792.0),
+            "legal" => (612.0, 1008.0),
+            "a4" | "" => (595.0, 842.0),
+            _ => {
+                return Err(HttpError::new_with_category(
+                    &format!("Page size {} is not supported", self.page_size),
+                    "report",
+                ))
+            }
+        };
+        let (width, height) = if self.landscape {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        Ok((self.width.unwrap_or(width), self.height.unwrap_or(height)))
+    }
+}
+
+/// 已放置在页面中的svg(已转换为pdf的xobject)
+struct Placement {
+    reference: Ref,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+/// 报表排版，坐标以页面左上角为原点，写入pdf时再转换
+struct ReportLayout {
+    pdf: Pdf,
+    next_ref: Ref,
+    page_width: f32,
+    page_height: f32,
+    margin: ReportMargin,
+    columns: usize,
+    pages: Vec<Vec<Placement>>,
+    // 当前的纵坐标
+    cursor: f32,
+    // 当前行已放置的图表数与行高
+    column: usize,
+    row_height: f32,
+}
+
+impl ReportLayout {
+    fn content_width(&self) -> f32 {
+        self.page_width - self.margin.left - self.margin.right
+    }
+    fn content_bottom(&self) -> f32 {
+        self.page_height - self.margin.bottom
+    }
+    fn new_page(&mut self) {
+        self.pages.push(vec![]);
+        self.cursor = self.margin.top;
+        self.column = 0;
+        self.row_height = 0.0;
+    }
+    /// 结束当前行，后续的内容从新的一行开始
+    fn finish_row(&mut self) {
+        if self.column != 0 {
+            self.cursor += self.row_height + GAP;
+        }
+        self.column = 0;
+        self.row_height = 0.0;
+    }
+    /// 将svg转换为xobject并放置在当前页
+    fn place(&mut self, tree: &Tree, x: f32, y: f32, width: f32, height: f32) -> HttpResult<()> {
+        let (chunk, reference) = tree_to_chunk(tree)?;
+        // chunk的引用从1开始，需重新编号后再合并
+        let mut refs = HashMap::new();
+        let chunk = chunk.renumber(|old| *refs.entry(old).or_insert_with(|| self.next_ref.bump()));
+        self.pdf.extend(&chunk);
+        let reference = refs[&reference];
+        if let Some(page) = self.pages.last_mut() {
+            page.push(Placement {
+                reference,
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+        Ok(())
+    }
+    fn add_chart(&mut self, svg: &str) -> HttpResult<()> {
+        let tree = parse_svg(svg)?;
+        let cell_width =
+            (self.content_width() - GAP * (self.columns - 1) as f32) / self.columns as f32;
+        let mut width = cell_width;
+        let mut height = tree.size().height() * cell_width / tree.size().width();
+        // 图表高度超出页面则按比例缩小
+        let max_height = self.content_bottom() - self.margin.top;
+        if height > max_height {
+            width = width * max_height / height;
+            height = max_height;
+        }
+        if self.column >= self.columns {
+            self.finish_row();
+        }
+        if self.cursor + height > self.content_bottom() {
+            self.new_page();
+        }
+        let x = self.margin.left + (cell_width + GAP) * self.column as f32;
+        let y = self.cursor;
+        self.place(&tree, x, y, width, height)?;
+        self.column += 1;
+        self.row_height = self.row_height.max(height);
+        Ok(())
+    }
+    fn add_text(&mut self, text: &TextBlock) -> HttpResult<()> {
+        self.finish_row();
+        let line_height = text.font_size * 1.4;
+        let lines = text.wrap(self.content_width());
+        let mut index = 0;
+        while index < lines.len() {
+            // 当前页可展示的行数，至少展示一行
+            let mut count = ((self.content_bottom() - self.cursor) / line_height) as usize;
+            if count == 0 {
+                self.new_page();
+                count = ((self.content_bottom() - self.cursor) / line_height).max(1.0) as usize;
+            }
+            let end = (index + count).min(lines.len());
+            let height = line_height * (end - index) as f32;
+            let svg = text.to_svg(&lines[index..end], self.content_width(), height);
+            let tree = parse_svg(&svg)?;
+            let (x, y, width) = (self.margin.left, self.cursor, self.content_width());
+            self.place(&tree, x, y, width, height)?;
+            self.cursor += height + GAP / 2.0;
+            index = end;
+        }
+        Ok(())
+    }
+    fn finish(mut self) -> Vec<u8> {
+        let catalog_id = self.next_ref;
+        let page_tree_id = Ref::new(catalog_id.get() + 1);
+        let mut next_id = page_tree_id.get() + 1;
+        let mut page_ids = vec![];
+        for _ in self.pages.iter() {
+            page_ids.push((Ref::new(next_id), Ref::new(next_id + 1)));
+            next_id += 2;
+        }
+        self.pdf.catalog(catalog_id).pages(page_tree_id);
+        self.pdf
+            .pages(page_tree_id)
+            .kids(page_ids.iter().map(|(page_id, _)| *page_id))
+            .count(page_ids.len() as i32);
+        for (placements, (page_id, content_id)) in self.pages.iter().zip(page_ids) {
+            let names: Vec<String> = (0..placements.len()).map(|i| format!("S{i}")).collect();
+            let mut page = self.pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, self.page_width, self.page_height))
+                .parent(page_tree_id)
+                .contents(content_id);
+            page.resources().x_objects().pairs(
+                names
+                    .iter()
+                    .zip(placements.iter())
+                    .map(|(name, item)| (Name(name.as_bytes()), item.reference)),
+            );
+            page.finish();
+
+            let mut content = Content::new();
+            for (name, item) in names.iter().zip(placements.iter()) {
+                // pdf的坐标原点为左下角
+                let y = self.page_height - item.y - item.height;
+                content
+                    .save_state()
+                    .transform([item.width, 0.0, 0.0, item.height, item.x, y])
+                    .x_object(Name(name.as_bytes()))
+                    .restore_state();
+            }
+            self.pdf.stream(content_id, &content.finish());
+        }
+        self.pdf.finish()
+    }
+}
+
+/// 文本块，转换为svg后使用与图表相同的字体绘制
+struct TextBlock {
+    text: String,
+    font_size: f32,
+    font_family: String,
+    font_color: String,
+    bold: bool,
+}
+
+impl TextBlock {
+    fn measure(&self, text: &str) -> f32 {
+        charts_rs::measure_text_width_family(&self.font_family, self.font_size, text)
+            .map(|b| b.width())
+            .unwrap_or_else(|_| text.chars().count() as f32 * self.font_size * 0.6)
+    }
+    /// 按宽度换行，优先在空格处换行
+    fn wrap(&self, width: f32) -> Vec<String> {
+        let mut lines = vec![];
+        for paragraph in self.text.split('\n') {
+            let mut line = String::new();
+            for c in paragraph.chars() {
+                line.push(c);
+                if line.chars().count() <= 1 || self.measure(&line) <= width {
+                    continue;
+                }
+                line.pop();
+                let rest = match line.rfind(' ') {
+                    Some(index) if index > 0 => {
+                        let rest = line[index + 1..].to_string();
+                        line.truncate(index);
+                        rest
+                    }
+                    _ => String::new(),
+                };
+                lines.push(line);
+                line = rest;
+                line.push(c);
+            }
+            lines.push(line);
+        }
+        lines
+    }
+    fn to_svg(&self, lines: &[String], width: f32, height: f32) -> String {
+        let line_height = self.font_size * 1.4;
+        let tspans: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let y = line_height * index as f32 + self.font_size;
+                format!(r#"<tspan x="0" y="{y}">{}</tspan>"#, escape_xml(line))
+            })
+            .collect();
+        let font_weight = if self.bold { "bold" } else { "normal" };
+        format!(
+            r#"<svg width="{width}" height="{height}" viewBox="0 0 {width} {height}" xmlns="http://www.w3.org/2000/svg"><text font-family="{}" font-size="{}" font-weight="{font_weight}" fill="{}" xml:space="preserve">{}</text></svg>"#,
+            escape_xml(&self.font_family),
+            self.font_size,
+            escape_xml(&self.font_color),
+            tspans.join("")
+        )
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 生成多页的pdf报表，图表按columns以网格的形式排列，
+/// 图表的svg由render_svg生成(与生成单个图表一致)
+pub fn render_report(
+    doc: &ReportDocument,
+    render_svg: impl Fn(&serde_json::Value) -> HttpResult<String>,
+) -> HttpResult<Vec<u8>> {
+    let (page_width, page_height) = doc.get_page_size()?;
+    let margin = doc.margin.clone();
+    if doc.columns == 0
+        || page_width - margin.left - margin.right <= 0.0
+        || page_height - margin.top - margin.bottom <= 0.0
+    {
+        return Err(HttpError::new_with_category(
+            "Page size, margin or columns is invalid",
+            "report",
+        ));
+    }
+    let mut layout = ReportLayout {
+        pdf: Pdf::new(),
+        next_ref: Ref::new(1),
+        page_width,
+        page_height,
+        margin,
+        columns: doc.columns,
+        pages: vec![],
+        cursor: 0.0,
+        column: 0,
+        row_height: 0.0,
+    };
+    layout.new_page();
+    if let Some(title) = &doc.title {
+        layout.add_text(&TextBlock {
+            text: title.clone(),
+            font_size: 20.0,
+            font_family: charts_rs::DEFAULT_FONT_FAMILY.to_string(),
+            font_color: "#333333".to_string(),
+            bold: true,
+        })?;
+    }
+    for (index, item) in doc.items.iter().enumerate() {
+        match item {
+            ReportItem::Chart { options } => {
+                let svg = render_svg(options).map_err(|mut err| {
+                    err.message = format!("Render item {index} fail: {}", err.message);
+                    err
+                })?;
+                layout.add_chart(&svg)?;
+            }
+            ReportItem::Text {
+                text,
+                font_size,
+                font_family,
+                font_color,
+                bold,
+            } => layout.add_text(&TextBlock {
+                text: text.clone(),
+                font_size: font_size.unwrap_or(12.0),
+                font_family: font_family
+                    .clone()
+                    .unwrap_or_else(|| charts_rs::DEFAULT_FONT_FAMILY.to_string()),
+                font_color: font_color.clone().unwrap_or_else(|| "#333333".to_string()),
+                bold: bold.unwrap_or_default(),
+            })?,
+            ReportItem::PageBreak => layout.new_page(),
+        }
+    }
+    Ok(layout.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReportDocument, ReportItem};
+    use serde_json::json;
+
+    fn new_report(items: serde_json::Value) -> ReportDocument {
+        serde_json::from_value(json!({ "items": items })).unwrap()
+    }
+
+    #[test]
+    fn check_chart_count() {
+        let chart = json!({"type": "chart", "options": {}});
+        let text = json!({"type": "text", "text": "hello"});
+        let doc = new_report(json!([chart, text, chart]));
+        assert!(matches!(doc.items[1], ReportItem::Text { .. }));
+        assert!(doc.check(2).is_ok());
+        let err = doc.check(1).unwrap_err();
+        assert_eq!(400, err.status);
+        assert_eq!("report_too_large", err.category);
+    }
+
+    #[test]
+    fn check_text_font_size() {
+        for font_size in [0.0, -12.0, 1000.0] {
+            let doc = new_report(json!([{"type": "text", "text": "a", "font_size": font_size}]));
+            assert_eq!(400, doc.check(10).unwrap_err().status, "{font_size}");
+        }
+        let doc = new_report(json!([{"type": "text", "text": "a", "font_size": 14}]));
+        assert!(doc.check(10).is_ok());
+    }
+}
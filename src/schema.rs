@@ -0,0 +1,820 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+// 支持的图表类型，未指定类型时使用bar
+pub static CHART_TYPES: [&str; 10] = [
+    "bar",
+    "line",
+    "horizontal_bar",
+    "pie",
+    "radar",
+    "table",
+    "scatter",
+    "candlestick",
+    "heatmap",
+    "multi_chart",
+];
+
+static ALIGNS: [&str; 3] = ["left", "center", "right"];
+
+// 字段的类型
+#[derive(Clone, Copy)]
+enum Kind {
+    Number,
+    Integer,
+    Bool,
+    String,
+    // 颜色，如#fff或#ffffff
+    Color,
+    Colors,
+    // 颜色列表，可以为null(使用默认颜色)
+    NullableColors,
+    // left,top,right,bottom的对象
+    Margin,
+    Align,
+    Aligns,
+    Strings,
+    Integers,
+    Numbers,
+    // 数值列表，可以为null(表示无数据)
+    NullableNumbers,
+    Enum(&'static [&'static str]),
+    Object(&'static [Field]),
+    // 对象，可以为null
+    NullableObject(&'static [Field]),
+    ArrayOf(&'static [Field]),
+    // 二维字符串数组，用于表格数据
+    Matrix,
+    // [index, value]的列表，用于热力图数据
+    HeatmapData,
+    // 子图表列表，每个子图表按其类型校验
+    ChildCharts,
+}
+
+struct Field {
+    name: &'static str,
+    kind: Kind,
+}
+
+const fn field(name: &'static str, kind: Kind) -> Field {
+    Field { name, kind }
+}
+
+static MARGIN_FIELDS: &[Field] = &[
+    field("left", Kind::Number),
+    field("top", Kind::Number),
+    field("right", Kind::Number),
+    field("bottom", Kind::Number),
+];
+
+// 由web服务处理的字段
+static PNG_FIELDS: &[Field] = &[
+    field("max_colors", Kind::Integer),
+    field("dithering", Kind::Number),
+    field("speed", Kind::Integer),
+    field("compression_level", Kind::Integer),
+    field("strip_metadata", Kind::Bool),
+];
+
+static SERVER_FIELDS: &[Field] = &[
+    field("type", Kind::Enum(&CHART_TYPES)),
+    field("theme", Kind::String),
+    field("quality", Kind::Integer),
+    field("strict", Kind::Bool),
+    field(
+        "format",
+        Kind::Enum(&["svg", "png", "webp", "avif", "jpeg", "pdf"]),
+    ),
+    field("background_color", Kind::Color),
+    field("scale", Kind::Number),
+    field("dpr", Kind::Number),
+    field("png", Kind::Object(PNG_FIELDS)),
+];
+
+static Y_AXIS_FIELDS: &[Field] = &[
+    field("axis_font_size", Kind::Number),
+    field("axis_font_color", Kind::Color),
+    field("axis_font_weight", Kind::String),
+    field("axis_stroke_color", Kind::Color),
+    field("axis_width", Kind::Number),
+    field("axis_split_number", Kind::Integer),
+    field("axis_name_gap", Kind::Number),
+    field("axis_formatter", Kind::String),
+    field("axis_margin", Kind::Margin),
+    field("axis_min", Kind::Number),
+    field("axis_max", Kind::Number),
+];
+
+static SERIES_FIELDS: &[Field] = &[
+    field("name", Kind::String),
+    field("data", Kind::NullableNumbers),
+    field("index", Kind::Integer),
+    field("y_axis_index", Kind::Integer),
+    field("label_show", Kind::Bool),
+    field("category", Kind::Enum(&["line", "bar"])),
+    field("start_index", Kind::Integer),
+    field(
+        "mark_lines",
+        Kind::ArrayOf(&[field("category", Kind::Enum(&["max", "min", "average"]))]),
+    ),
+    field(
+        "mark_points",
+        Kind::ArrayOf(&[field("category", Kind::Enum(&["max", "min"]))]),
+    ),
+    field("colors", Kind::NullableColors),
+    field("stroke_dash_array", Kind::String),
+];
+
+// 标题相关字段，所有图表均支持
+static TITLE_FIELDS: &[Field] = &[
+    field("width", Kind::Number),
+    field("height", Kind::Number),
+    field("x", Kind::Number),
+    field("y", Kind::Number),
+    field("font_family", Kind::String),
+    field("title_text", Kind::String),
+    field("title_font_size", Kind::Number),
+    field("title_font_color", Kind::Color),
+    field("title_font_weight", Kind::String),
+    field("title_margin", Kind::Margin),
+    field("title_align", Kind::Align),
+    field("title_height", Kind::Number),
+    field("sub_title_text", Kind::String),
+    field("sub_title_font_size", Kind::Number),
+    field("sub_title_font_color", Kind::Color),
+    field("sub_title_font_weight", Kind::String),
+    field("sub_title_margin", Kind::Margin),
+    field("sub_title_align", Kind::Align),
+    field("sub_title_height", Kind::Number),
+];
+
+// 除表格与多图表外的公共字段
+static COMMON_FIELDS: &[Field] = &[
+    field("margin", Kind::Margin),
+    field("legend_font_size", Kind::Number),
+    field("legend_font_color", Kind::Color),
+    field("legend_font_weight", Kind::String),
+    field("legend_align", Kind::Align),
+    field("legend_margin", Kind::Margin),
+    field(
+        "legend_category",
+        Kind::Enum(&["normal", "rect", "round_rect", "circle"]),
+    ),
+    field("legend_show", Kind::Bool),
+    field("x_axis_data", Kind::Strings),
+    field("x_axis_height", Kind::Number),
+    field("x_axis_stroke_color", Kind::Color),
+    field("x_axis_font_size", Kind::Number),
+    field("x_axis_font_color", Kind::Color),
+    field("x_axis_font_weight", Kind::String),
+    field("x_axis_name_gap", Kind::Number),
+    field("x_axis_name_rotate", Kind::Number),
+    field("x_axis_margin", Kind::Margin),
+    field("x_boundary_gap", Kind::Bool),
+    field("y_axis_configs", Kind::ArrayOf(Y_AXIS_FIELDS)),
+    field("grid_stroke_color", Kind::Color),
+    field("grid_stroke_width", Kind::Number),
+    field("series_stroke_width", Kind::Number),
+    field("series_label_font_color", Kind::Color),
+    field("series_label_font_size", Kind::Number),
+    field("series_label_font_weight", Kind::String),
+    field("series_label_formatter", Kind::String),
+    field("series_colors", Kind::Colors),
+    field(
+        "series_symbol",
+        Kind::NullableObject(&[field("color", Kind::Color), field("radius", Kind::Number)]),
+    ),
+    field("series_smooth", Kind::Bool),
+    field("series_fill", Kind::Bool),
+    field("series_list", Kind::ArrayOf(SERIES_FIELDS)),
+];
+
+static AXIS_HIDDEN_FIELDS: &[Field] = &[
+    field("x_axis_hidden", Kind::Bool),
+    field("y_axis_hidden", Kind::Bool),
+];
+
+static BAR_FIELDS: &[Field] = &[field("radius", Kind::Number)];
+
+static HORIZONTAL_BAR_FIELDS: &[Field] = &[field(
+    "series_label_position",
+    Kind::Enum(&["inside", "top", "right", "bottom", "left"]),
+)];
+
+static PIE_FIELDS: &[Field] = &[
+    field("radius", Kind::Number),
+    field("inner_radius", Kind::Number),
+    field("rose_type", Kind::Bool),
+    field("border_radius", Kind::Number),
+];
+
+static RADAR_FIELDS: &[Field] = &[field(
+    "indicators",
+    Kind::ArrayOf(&[field("name", Kind::String), field("max", Kind::Number)]),
+)];
+
+static SCATTER_FIELDS: &[Field] = &[
+    field("series_symbol_sizes", Kind::Numbers),
+    field("x_axis_config", Kind::Object(Y_AXIS_FIELDS)),
+];
+
+static CANDLESTICK_FIELDS: &[Field] = &[
+    field("candlestick_up_color", Kind::Color),
+    field("candlestick_up_border_color", Kind::Color),
+    field("candlestick_down_color", Kind::Color),
+    field("candlestick_down_border_color", Kind::Color),
+];
+
+static HEATMAP_FIELDS: &[Field] = &[
+    field("y_axis_data", Kind::Strings),
+    field(
+        "series",
+        Kind::Object(&[
+            field("min", Kind::Number),
+            field("max", Kind::Number),
+            field("min_color", Kind::Color),
+            field("max_color", Kind::Color),
+            field("min_font_color", Kind::Color),
+            field("max_font_color", Kind::Color),
+            field("data", Kind::HeatmapData),
+        ]),
+    ),
+];
+
+static TABLE_FIELDS: &[Field] = &[
+    field("data", Kind::Matrix),
+    field("spans", Kind::Numbers),
+    field("text_aligns", Kind::Aligns),
+    field("border_color", Kind::Color),
+    field("outlined", Kind::Bool),
+    field("header_row_padding", Kind::Margin),
+    field("header_row_height", Kind::Number),
+    field("header_font_size", Kind::Number),
+    field("header_font_weight", Kind::String),
+    field("header_font_color", Kind::Color),
+    field("header_background_color", Kind::Color),
+    field("body_row_padding", Kind::Margin),
+    field("body_row_height", Kind::Number),
+    field("body_font_size", Kind::Number),
+    field("body_font_color", Kind::Color),
+    field("body_background_colors", Kind::Colors),
+    field(
+        "cell_styles",
+        Kind::ArrayOf(&[
+            field("font_color", Kind::Color),
+            field("font_weight", Kind::String),
+            field("background_color", Kind::Color),
+            field("indexes", Kind::Integers),
+        ]),
+    ),
+];
+
+static MULTI_CHART_FIELDS: &[Field] = &[
+    field("margin", Kind::Margin),
+    field("gap", Kind::Number),
+    field("child_charts", Kind::ChildCharts),
+];
+
+/// 获取图表类型对应的字段列表
+fn get_chart_fields(chart_type: &str) -> Option<Vec<&'static [Field]>> {
+    let mut groups = vec![SERVER_FIELDS, TITLE_FIELDS];
+    match chart_type {
+        "bar" => groups.extend([COMMON_FIELDS, AXIS_HIDDEN_FIELDS, BAR_FIELDS]),
+        "line" => groups.extend([COMMON_FIELDS, AXIS_HIDDEN_FIELDS]),
+        "horizontal_bar" => groups.extend([COMMON_FIELDS, HORIZONTAL_BAR_FIELDS]),
+        "pie" => groups.extend([COMMON_FIELDS, PIE_FIELDS]),
+        "radar" => groups.extend([COMMON_FIELDS, RADAR_FIELDS]),
+        "scatter" => groups.extend([COMMON_FIELDS, AXIS_HIDDEN_FIELDS, SCATTER_FIELDS]),
+        "candlestick" => groups.extend([COMMON_FIELDS, AXIS_HIDDEN_FIELDS, CANDLESTICK_FIELDS]),
+        "heatmap" => groups.extend([COMMON_FIELDS, AXIS_HIDDEN_FIELDS, HEATMAP_FIELDS]),
+        "table" => groups.push(TABLE_FIELDS),
+        "multi_chart" => groups = vec![SERVER_FIELDS, MULTI_CHART_FIELDS],
+        _ => return None,
+    }
+    Some(groups)
+}
+
+fn color_schema() -> Value {
+    json!({
+        "type": "string",
+        "pattern": "^#([0-9a-fA-F]{3}|[0-9a-fA-F]{6})$"
+    })
+}
+
+fn object_schema<'a>(fields: impl Iterator<Item = &'a Field>) -> Value {
+    let mut properties = Map::new();
+    for item in fields {
+        properties.insert(item.name.to_string(), kind_schema(item.kind));
+    }
+    json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false
+    })
+}
+
+fn kind_schema(kind: Kind) -> Value {
+    match kind {
+        Kind::Number => json!({ "type": "number" }),
+        Kind::Integer => json!({ "type": "integer", "minimum": 0 }),
+        Kind::Bool => json!({ "type": "boolean" }),
+        Kind::String => json!({ "type": "string" }),
+        Kind::Color => color_schema(),
+        Kind::Colors => json!({ "type": "array", "items": color_schema() }),
+        Kind::NullableColors => json!({
+            "type": "array",
+            "items": { "anyOf": [color_schema(), { "type": "null" }] }
+        }),
+        Kind::Margin => object_schema(MARGIN_FIELDS.iter()),
+        Kind::Align => json!({ "enum": ALIGNS }),
+        Kind::Aligns => json!({ "type": "array", "items": { "enum": ALIGNS } }),
+        Kind::Strings => json!({ "type": "array", "items": { "type": "string" } }),
+        Kind::Integers => json!({
+            "type": "array",
+            "items": { "type": "integer", "minimum": 0 }
+        }),
+        Kind::Numbers => json!({ "type": "array", "items": { "type": "number" } }),
+        Kind::NullableNumbers => json!({
+            "type": "array",
+            "items": { "type": ["number", "null"] }
+        }),
+        Kind::Enum(values) => json!({ "enum": values }),
+        Kind::Object(fields) => object_schema(fields.iter()),
+        Kind::NullableObject(fields) => json!({
+            "anyOf": [object_schema(fields.iter()), { "type": "null" }]
+        }),
+        Kind::ArrayOf(fields) => json!({
+            "type": "array",
+            "items": object_schema(fields.iter())
+        }),
+        Kind::Matrix => json!({
+            "type": "array",
+            "minItems": 1,
+            "items": { "type": "array", "items": { "type": "string" } }
+        }),
+        Kind::HeatmapData => json!({
+            "type": "array",
+            "items": {
+                "type": "array",
+                "prefixItems": [
+                    { "type": "integer", "minimum": 0 },
+                    { "type": "number" }
+                ],
+                "minItems": 2,
+                "maxItems": 2
+            }
+        }),
+        Kind::ChildCharts => {
+            // 子图表根据type引用对应的定义，未指定type则为bar
+            let rules: Vec<Value> = CHART_TYPES
+                .iter()
+                .filter(|chart_type| **chart_type != "multi_chart")
+                .map(|chart_type| {
+                    json!({
+                        "if": { "properties": { "type": { "const": chart_type } } },
+                        "then": { "$ref": format!("#/$defs/{chart_type}") }
+                    })
+                })
+                .collect();
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "allOf": rules
+                }
+            })
+        }
+    }
+}
+
+/// 生成图表类型的schema(不包括json schema的公共属性)
+fn chart_schema(chart_type: &str) -> Option<Value> {
+    let groups = get_chart_fields(chart_type)?;
+    let mut schema = object_schema(groups.iter().flat_map(|fields| fields.iter()));
+    if let Some(properties) = schema
+        .get_mut("properties")
+        .and_then(|value| value.as_object_mut())
+    {
+        properties.insert("type".to_string(), json!({ "enum": [chart_type] }));
+    }
+    Some(schema)
+}
+
+/// 获取图表类型对应的json schema
+pub fn get_schema(chart_type: &str) -> Option<Value> {
+    let mut schema = chart_schema(chart_type)?;
+    if let Some(object) = schema.as_object_mut() {
+        object.insert(
+            "$schema".to_string(),
+            json!("https://json-schema.org/draft/2020-12/schema"),
+        );
+        object.insert("title".to_string(), json!(chart_type));
+        if chart_type == "multi_chart" {
+            let mut defs = Map::new();
+            for child_type in CHART_TYPES.iter().filter(|v| **v != "multi_chart") {
+                if let Some(value) = chart_schema(child_type) {
+                    defs.insert(child_type.to_string(), value);
+                }
+            }
+            object.insert("$defs".to_string(), Value::Object(defs));
+        }
+    }
+    Some(schema)
+}
+
+/// 图表类型的顶层字段是否为文本类型(字符串、颜色、对齐方式以及枚举)，
+/// 未知的图表类型按bar处理
+pub fn is_text_field(chart_type: &str, name: &str) -> bool {
+    let groups = get_chart_fields(chart_type)
+        .or_else(|| get_chart_fields("bar"))
+        .unwrap_or_default();
+    groups
+        .iter()
+        .flat_map(|fields| fields.iter())
+        .find(|item| item.name == name)
+        .is_some_and(|item| {
+            matches!(
+                item.kind,
+                Kind::String | Kind::Color | Kind::Align | Kind::Enum(_)
+            )
+        })
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct Problem {
+    // 出错字段的json pointer
+    pub path: String,
+    // 出错类型
+    pub category: String,
+    // 出错信息
+    pub message: String,
+}
+
+// 校验过程中记录出错信息
+struct Validator {
+    problems: Vec<Problem>,
+}
+
+/// 转换为json pointer的格式
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn is_color(value: &str) -> bool {
+    if let Some(hex) = value.strip_prefix('#') {
+        return (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    false
+}
+
+impl Validator {
+    fn add(&mut self, path: &str, category: &str, message: String) {
+        self.problems.push(Problem {
+            path: path.to_string(),
+            category: category.to_string(),
+            message,
+        });
+    }
+    fn invalid_type(&mut self, path: &str, expected: &str, value: &Value) {
+        self.add(
+            path,
+            "invalid_type",
+            format!("expected {expected}, got {}", type_name(value)),
+        );
+    }
+    fn validate_object<'a>(
+        &mut self,
+        path: &str,
+        value: &Value,
+        fields: impl Iterator<Item = &'a Field> + Clone,
+    ) {
+        let Some(object) = value.as_object() else {
+            self.invalid_type(path, "object", value);
+            return;
+        };
+        for (key, item) in object.iter() {
+            let item_path = format!("{path}/{}", escape_pointer(key));
+            match fields.clone().find(|field| field.name == key) {
+                Some(field) => self.validate_kind(&item_path, item, field.kind),
+                None => self.add(&item_path, "unknown_field", format!("unknown field {key}")),
+            }
+        }
+    }
+    fn validate_array(&mut self, path: &str, value: &Value, item_kind: Kind) {
+        let Some(items) = value.as_array() else {
+            self.invalid_type(path, "array", value);
+            return;
+        };
+        for (index, item) in items.iter().enumerate() {
+            self.validate_kind(&format!("{path}/{index}"), item, item_kind);
+        }
+    }
+    fn validate_kind(&mut self, path: &str, value: &Value, kind: Kind) {
+        match kind {
+            Kind::Number => {
+                if !value.is_number() {
+                    self.invalid_type(path, "number", value);
+                }
+            }
+            Kind::Integer => {
+                if !value.is_u64() {
+                    self.invalid_type(path, "non-negative integer", value);
+                }
+            }
+            Kind::Bool => {
+                if !value.is_boolean() {
+                    self.invalid_type(path, "boolean", value);
+                }
+            }
+            Kind::String => {
+                if !value.is_string() {
+                    self.invalid_type(path, "string", value);
+                }
+            }
+            Kind::Color => match value.as_str() {
+                Some(color) => {
+                    if !is_color(color) {
+                        self.add(
+                            path,
+                            "invalid_value",
+                            format!("invalid color {color}, expected #rgb or #rrggbb"),
+                        );
+                    }
+                }
+                None => self.invalid_type(path, "color string", value),
+            },
+            Kind::Colors => self.validate_array(path, value, Kind::Color),
+            Kind::NullableColors => {
+                let Some(items) = value.as_array() else {
+                    self.invalid_type(path, "array", value);
+                    return;
+                };
+                for (index, item) in items.iter().enumerate() {
+                    if !item.is_null() {
+                        self.validate_kind(&format!("{path}/{index}"), item, Kind::Color);
+                    }
+                }
+            }
+            Kind::Margin => self.validate_object(path, value, MARGIN_FIELDS.iter()),
+            Kind::Align => self.validate_kind(path, value, Kind::Enum(&ALIGNS)),
+            Kind::Aligns => self.validate_array(path, value, Kind::Align),
+            Kind::Strings => self.validate_array(path, value, Kind::String),
+            Kind::Integers => self.validate_array(path, value, Kind::Integer),
+            Kind::Numbers => self.validate_array(path, value, Kind::Number),
+            Kind::NullableNumbers => {
+                let Some(items) = value.as_array() else {
+                    self.invalid_type(path, "array", value);
+                    return;
+                };
+                for (index, item) in items.iter().enumerate() {
+                    if !item.is_null() && !item.is_number() {
+                        self.invalid_type(&format!("{path}/{index}"), "number or null", item);
+                    }
+                }
+            }
+            Kind::Enum(values) => match value.as_str() {
+                Some(v) => {
+                    if !values.contains(&v) {
+                        self.add(
+                            path,
+                            "invalid_value",
+                            format!("invalid value {v}, expected one of {}", values.join(",")),
+                        );
+                    }
+                }
+                None => self.invalid_type(path, "string", value),
+            },
+            Kind::Object(fields) => self.validate_object(path, value, fields.iter()),
+            Kind::NullableObject(fields) => {
+                if !value.is_null() {
+                    self.validate_object(path, value, fields.iter());
+                }
+            }
+            Kind::ArrayOf(fields) => self.validate_array(path, value, Kind::Object(fields)),
+            Kind::Matrix => {
+                let Some(rows) = value.as_array() else {
+                    self.invalid_type(path, "array", value);
+                    return;
+                };
+                if rows.is_empty() {
+                    self.add(path, "invalid_value", "table data is empty".to_string());
+                }
+                for (index, row) in rows.iter().enumerate() {
+                    self.validate_array(&format!("{path}/{index}"), row, Kind::String);
+                }
+            }
+            Kind::HeatmapData => {
+                let Some(items) = value.as_array() else {
+                    self.invalid_type(path, "array", value);
+                    return;
+                };
+                for (index, item) in items.iter().enumerate() {
+                    let item_path = format!("{path}/{index}");
+                    match item.as_array() {
+                        Some(arr) if arr.len() == 2 => {
+                            self.validate_kind(&format!("{item_path}/0"), &arr[0], Kind::Integer);
+                            self.validate_kind(&format!("{item_path}/1"), &arr[1], Kind::Number);
+                        }
+                        _ => self.invalid_type(&item_path, "[index, value]", item),
+                    }
+                }
+            }
+            Kind::ChildCharts => {
+                let Some(items) = value.as_array() else {
+                    self.invalid_type(path, "array", value);
+                    return;
+                };
+                for (index, item) in items.iter().enumerate() {
+                    let item_path = format!("{path}/{index}");
+                    let chart_type = item.get("type").and_then(|v| v.as_str()).unwrap_or("bar");
+                    if chart_type == "multi_chart" {
+                        self.add(
+                            &format!("{item_path}/type"),
+                            "invalid_value",
+                            "multi chart can not be nested".to_string(),
+                        );
+                        continue;
+                    }
+                    self.validate_chart(&item_path, item, chart_type);
+                }
+            }
+        }
+    }
+    fn validate_chart(&mut self, path: &str, value: &Value, chart_type: &str) {
+        let Some(groups) = get_chart_fields(chart_type) else {
+            self.add(
+                &format!("{path}/type"),
+                "invalid_value",
+                format!(
+                    "unsupported chart type {chart_type}, expected one of {}",
+                    CHART_TYPES.join(",")
+                ),
+            );
+            return;
+        };
+        self.validate_object(path, value, groups.iter().flat_map(|fields| fields.iter()));
+        self.validate_series_length(path, value, chart_type);
+    }
+    /// 校验数据系列的数量是否与x轴的数量一致
+    fn validate_series_length(&mut self, path: &str, value: &Value, chart_type: &str) {
+        // 蜡烛图每个点有4个值
+        let count = match chart_type {
+            "bar" | "line" | "horizontal_bar" => 1,
+            "candlestick" => 4,
+            _ => return,
+        };
+        let Some(x_axis_data) = value.get("x_axis_data").and_then(|v| v.as_array()) else {
+            return;
+        };
+        let Some(series_list) = value.get("series_list").and_then(|v| v.as_array()) else {
+            return;
+        };
+        if x_axis_data.is_empty() {
+            return;
+        }
+        let expected = x_axis_data.len() * count;
+        for (index, series) in series_list.iter().enumerate() {
+            let Some(data) = series.get("data").and_then(|v| v.as_array()) else {
+                continue;
+            };
+            let start_index = series
+                .get("start_index")
+                .and_then(|v| v.as_u64())
+                .unwrap_or_default() as usize;
+            let size = start_index * count + data.len();
+            if size != expected {
+                self.add(
+                    &format!("{path}/series_list/{index}/data"),
+                    "length_mismatch",
+                    format!(
+                        "series data length is {size}, expected {expected} (x_axis_data length is {})",
+                        x_axis_data.len()
+                    ),
+
+                );
+            }
+        }
+    }
+}
+
+/// 校验图表参数，返回所有的出错信息
+pub fn validate(value: &Value) -> Vec<Problem> {
+    let mut validator = Validator { problems: vec![] };
+    // type非字符串时在校验字段时会记录
+    let chart_type = value.get("type").and_then(|v| v.as_str()).unwrap_or("bar");
+    validator.validate_chart("", value, chart_type);
+    validator.problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_schema, is_text_field, validate, Problem};
+    use serde_json::json;
+
+    fn get_problems(problems: Vec<Problem>) -> Vec<(String, String)> {
+        problems
+            .into_iter()
+            .map(|item| (item.path, item.category))
+            .collect()
+    }
+
+    #[test]
+    fn validate_valid_chart() {
+        let value = json!({
+            "type": "bar",
+            "width": 600,
+            "title_text": "Sales",
+            "margin": {"left": 5, "top": 5},
+            "x_axis_data": ["Mon", "Tue"],
+            "series_list": [{"name": "Email", "data": [120.0, 132.0]}],
+        });
+        assert!(validate(&value).is_empty());
+    }
+
+    #[test]
+    fn validate_unknown_field() {
+        let value = json!({
+            "type": "bar",
+            "widht": 600,
+            "margin": {"left": 5, "a/b": 5},
+        });
+        assert_eq!(
+            vec![
+                ("/margin/a~1b".to_string(), "unknown_field".to_string()),
+                ("/widht".to_string(), "unknown_field".to_string()),
+            ],
+            get_problems(validate(&value))
+        );
+    }
+
+    #[test]
+    fn validate_invalid_type() {
+        let value = json!({
+            "type": "bar",
+            "width": "600",
+            "series_list": [{"name": "Email", "data": [120.0, "132"]}],
+        });
+        assert_eq!(
+            vec![
+                (
+                    "/series_list/0/data/1".to_string(),
+                    "invalid_type".to_string()
+                ),
+                ("/width".to_string(), "invalid_type".to_string()),
+            ],
+            get_problems(validate(&value))
+        );
+        let problems = validate(&json!({"width": true}));
+        assert_eq!("expected number, got boolean", problems[0].message);
+    }
+
+    #[test]
+    fn validate_length_mismatch() {
+        let value = json!({
+            "type": "line",
+            "x_axis_data": ["Mon", "Tue", "Wed"],
+            "series_list": [
+                {"name": "Email", "data": [120.0, 132.0, 101.0]},
+                {"name": "Video", "data": [120.0, 132.0]},
+                {"name": "Search", "data": [132.0], "start_index": 2},
+            ],
+        });
+        assert_eq!(
+            vec![(
+                "/series_list/1/data".to_string(),
+                "length_mismatch".to_string()
+            )],
+            get_problems(validate(&value))
+        );
+    }
+
+    #[test]
+    fn validate_chart_type() {
+        let problems = get_problems(validate(&json!({"type": "unknown"})));
+        assert_eq!(
+            vec![("/type".to_string(), "invalid_value".to_string())],
+            problems
+        );
+        assert!(get_schema("unknown").is_none());
+        assert!(get_schema("multi_chart").unwrap().get("$defs").is_some());
+    }
+
+    #[test]
+    fn text_field() {
+        assert!(is_text_field("bar", "title_text"));
+        assert!(!is_text_field("bar", "width"));
+        // 未知的图表类型按bar处理
+        assert!(is_text_field("unknown", "title_text"));
+    }
+}
@@ -0,0 +1,198 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tracing::info;
+
+use crate::codec::{decode_compact, encode_compact, Encoding};
+use crate::config::must_new_share_config;
+use crate::error::{HttpError, HttpResult};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 签名截取的字节数，128位已足够且链接更短
+static SIGNATURE_SIZE: usize = 16;
+// 保存的图表参数id长度(sha256的hex)
+static STORED_ID_SIZE: usize = 16;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ShareResult {
+    // 链接的id
+    pub id: String,
+    // 分享链接
+    pub url: String,
+    // 过期时间(unix时间戳，秒)，为空则不过期
+    pub expires: Option<i64>,
+}
+
+/// 获取签名的密钥，未配置则不支持分享
+fn get_share_key() -> HttpResult<&'static str> {
+    let key = &must_new_share_config().key;
+    if key.is_empty() {
+        return Err(HttpError::new_with_category_status(
+            "Share key is not configured",
+            "share_disabled",
+            501,
+        ));
+    }
+    Ok(key)
+}
+
+/// 根据id、输出格式与过期时间生成签名的mac，过期时间为0表示不过期，
+/// 签名包含输出格式，因此链接仅可获取分享时指定的格式
+fn new_mac(key: &str, id: &str, ext: &str, expires: i64) -> HttpResult<HmacSha256> {
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes())
+        .map_err(|err| HttpError::new_with_category(&err.to_string(), "share"))?;
+    mac.update(format!("{id}.{ext}:{expires}").as_bytes());
+    Ok(mac)
+}
+
+/// 生成签名(截取后以base64url编码)
+fn sign(key: &str, id: &str, ext: &str, expires: Option<i64>) -> HttpResult<String> {
+    let mac = new_mac(key, id, ext, expires.unwrap_or_default())?;
+    Ok(URL_SAFE_NO_PAD.encode(&mac.finalize().into_bytes()[..SIGNATURE_SIZE]))
+}
+
+/// 校验签名与有效期，签名不正确返回403，已过期返回410
+fn verify(key: &str, id: &str, ext: &str, signature: &str, expires: Option<i64>) -> HttpResult<()> {
+    let signature = URL_SAFE_NO_PAD.decode(signature).unwrap_or_default();
+    let mac = new_mac(key, id, ext, expires.unwrap_or_default())?;
+    // 常量时间比较，避免通过耗时推测签名
+    if signature.len() != SIGNATURE_SIZE || mac.verify_truncated_left(&signature).is_err() {
+        return Err(HttpError::new_with_category_status(
+            "Share signature is invalid",
+            "share_signature",
+            403,
+        ));
+    }
+    if let Some(expires) = expires {
+        if expires <= Utc::now().timestamp() {
+            return Err(HttpError::new_with_category_status(
+                "Share link has expired",
+                "share_expired",
+                410,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 是否保存的图表参数id(固定长度的hex)
+fn is_stored_id(id: &str) -> bool {
+    id.len() == STORED_ID_SIZE && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// 生成图表的分享链接，若配置了保存目录则将参数保存为文件，
+/// 否则压缩参数作为id。ttl为空时使用配置的有效期，为0则不过期
+pub fn new_share(
+    value: &serde_json::Value,
+    ext: &str,
+    ttl: Option<Duration>,
+) -> HttpResult<ShareResult> {
+    let key = get_share_key()?;
+    let config = must_new_share_config();
+    let data = value.to_string();
+    let id = if config.path.is_empty() {
+        encode_compact(data.as_bytes(), Encoding::Deflate)?
+    } else {
+        let id = hex::encode(Sha256::digest(data.as_bytes()))[..STORED_ID_SIZE].to_string();
+        let file = Path::new(&config.path).join(format!("{id}.json"));
+        // 相同的参数id一致，已存在则无需再保存
+        if !file.exists() {
+            fs::create_dir_all(&config.path)
+                .and_then(|_| fs::write(&file, &data))
+                .map_err(|err| HttpError::new_with_category(&err.to_string(), "share"))?;
+            info!(id, "save share options success");
+        }
+        id
+    };
+    let ttl = ttl.unwrap_or(config.ttl);
+    let expires = if ttl.is_zero() {
+        None
+    } else {
+        Some(Utc::now().timestamp() + ttl.as_secs().max(1) as i64)
+    };
+    let signature = sign(key, &id, ext, expires)?;
+    let mut url = format!("{}/c/{id}.{ext}?sig={signature}", config.base_url);
+    if let Some(expires) = expires {
+        url = format!("{url}&exp={expires}");
+    }
+    Ok(ShareResult { id, url, expires })
+}
+
+/// 校验分享链接(id与输出格式)的签名与有效期，通过后返回图表参数
+pub fn get_share(
+    id: &str,
+    ext: &str,
+    signature: &str,
+    expires: Option<i64>,
+) -> HttpResult<serde_json::Value> {
+    verify(get_share_key()?, id, ext, signature, expires)?;
+    let path = &must_new_share_config().path;
+    let data = if !path.is_empty() && is_stored_id(id) {
+        fs::read(Path::new(&path).join(format!("{id}.json"))).map_err(|_| {
+            HttpError::new_with_category_status("Share options not found", "share", 404)
+        })?
+    } else {
+        decode_compact(id, Encoding::Deflate)?
+    };
+    Ok(serde_json::from_slice(&data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign, verify};
+    use chrono::Utc;
+
+    static KEY: &str = "share-secret";
+
+    #[test]
+    fn sign_and_verify() {
+        let signature = sign(KEY, "abc", "png", None).unwrap();
+        assert!(verify(KEY, "abc", "png", &signature, None).is_ok());
+
+        let expires = Some(Utc::now().timestamp() + 60);
+        let signature = sign(KEY, "abc", "png", expires).unwrap();
+        assert!(verify(KEY, "abc", "png", &signature, expires).is_ok());
+    }
+
+    #[test]
+    fn verify_tampered() {
+        let expires = Some(Utc::now().timestamp() + 60);
+        let signature = sign(KEY, "abc", "png", expires).unwrap();
+        let status = |result: Result<(), crate::error::HttpError>| result.unwrap_err().status;
+        // 修改id、格式、过期时间或密钥均无法通过校验
+        assert_eq!(403, status(verify(KEY, "abd", "png", &signature, expires)));
+        assert_eq!(403, status(verify(KEY, "abc", "avif", &signature, expires)));
+        assert_eq!(403, status(verify(KEY, "abc", "png", &signature, None)));
+        assert_eq!(
+            403,
+            status(verify(
+                KEY,
+                "abc",
+                "png",
+                &signature,
+                expires.map(|v| v + 1)
+            ))
+        );
+        assert_eq!(
+            403,
+            status(verify("other", "abc", "png", &signature, expires))
+        );
+        assert_eq!(403, status(verify(KEY, "abc", "png", "invalid", expires)));
+        assert_eq!(403, status(verify(KEY, "abc", "png", "", expires)));
+    }
+
+    #[test]
+    fn verify_expired() {
+        let expires = Some(Utc::now().timestamp() - 1);
+        let signature = sign(KEY, "abc", "png", expires).unwrap();
+        let err = verify(KEY, "abc", "png", &signature, expires).unwrap_err();
+        assert_eq!(410, err.status);
+        assert_eq!("share_expired", err.category);
+    }
+}
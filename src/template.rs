@@ -0,0 +1,189 @@
+use glob::glob;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+use tracing::{error, info};
+
+use crate::error::{HttpError, HttpResult};
+
+static TEMPLATES: Lazy<RwLock<HashMap<String, serde_json::Value>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// 模板名称仅支持字母、数字、-以及_
+pub fn is_valid_template_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 添加模板，模板需要为json对象
+pub fn add_template(name: &str, value: serde_json::Value) -> HttpResult<()> {
+    if !is_valid_template_name(name) {
+        return Err(HttpError::new_with_category(
+            &format!("Template name {name} is invalid"),
+            "template",
+        ));
+    }
+    if !value.is_object() {
+        return Err(HttpError::new_with_category(
+            "Template should be a json object",
+            "template",
+        ));
+    }
+    if let Ok(mut templates) = TEMPLATES.write() {
+        templates.insert(name.to_string(), value);
+    }
+    Ok(())
+}
+
+/// 获取模板
+pub fn get_template(name: &str) -> Option<serde_json::Value> {
+    TEMPLATES.read().ok()?.get(name).cloned()
+}
+
+/// 获取所有模板名称
+pub fn list_template_name() -> Vec<String> {
+    let mut names: Vec<String> = TEMPLATES
+        .read()
+        .map(|templates| templates.keys().cloned().collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// 从目录中加载模板(json文件)，文件名为模板名称
+pub fn load_templates(dir: &str) {
+    for entry in glob(&format!("{dir}/*.json"))
+        .expect("Failed to read glob pattern")
+        .flatten()
+    {
+        let file = entry.to_string_lossy().to_string();
+        let name = entry
+            .file_stem()
+            .map(|value| value.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let result = fs::read(Path::new(&file))
+            .map_err(|err| HttpError::new_with_category(&err.to_string(), "template"))
+            .and_then(|buf| Ok(serde_json::from_slice::<serde_json::Value>(&buf)?))
+            .and_then(|value| add_template(&name, value));
+        match result {
+            Ok(()) => info!(name, file, "load template success"),
+            Err(err) => error!(name, file, error = err.message, "load template fail"),
+        }
+    }
+}
+
+/// 将data深度合并至target，对象则逐个字段合并，其它类型则直接替换
+fn merge(target: &mut serde_json::Value, data: serde_json::Value) {
+    match (target, data) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(data)) => {
+            for (key, value) in data {
+                match target.get_mut(&key) {
+                    Some(item) => merge(item, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, data) => *target = data,
+    }
+}
+
+/// 如果参数指定了template，则将data合并至模板中，
+/// 否则直接返回原参数
+pub fn expand_template(value: serde_json::Value) -> HttpResult<serde_json::Value> {
+    let Some(name) = value.get("template") else {
+        return Ok(value);
+    };
+    let name = name.as_str().unwrap_or_default().to_string();
+    let mut result = get_template(&name).ok_or_else(|| {
+        HttpError::new_with_category_status(
+            &format!("Template {name} is not found"),
+            "template",
+            404,
+        )
+    })?;
+    if let serde_json::Value::Object(mut value) = value {
+        if let Some(data) = value.remove("data") {
+            merge(&mut result, data);
+        }
+        // 除模板相关的字段外，其它字段(如format)保留
+        value.remove("template");
+        merge(&mut result, serde_json::Value::Object(value));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_template, expand_template, is_valid_template_name, merge};
+    use serde_json::json;
+
+    #[test]
+    fn valid_template_name() {
+        assert!(is_valid_template_name("sales_2024-q1"));
+        assert!(!is_valid_template_name(""));
+        assert!(!is_valid_template_name("../sales"));
+        assert!(add_template("sales", json!([])).is_err());
+    }
+
+    #[test]
+    fn merge_deep() {
+        let mut target = json!({
+            "type": "bar",
+            "title": {"text": "Sales", "font_size": 18},
+            "series_list": [{"name": "Email", "data": [1.0, 2.0]}],
+        });
+        merge(
+            &mut target,
+            json!({
+                "title": {"text": "Orders"},
+                "series_list": [{"name": "Video", "data": [3.0]}],
+                "width": 800,
+            }),
+        );
+        // 对象逐个字段合并，数组直接替换
+        assert_eq!(
+            json!({
+                "type": "bar",
+                "title": {"text": "Orders", "font_size": 18},
+                "series_list": [{"name": "Video", "data": [3.0]}],
+                "width": 800,
+            }),
+            target
+        );
+    }
+
+    #[test]
+    fn expand() {
+        add_template(
+            "template_test",
+            json!({"type": "line", "title": {"text": "Sales", "font_size": 18}}),
+        )
+        .unwrap();
+        let value = expand_template(json!({
+            "template": "template_test",
+            "data": {"title": {"text": "Orders"}},
+            "format": "png",
+        }))
+        .unwrap();
+        assert_eq!(
+            json!({
+                "type": "line",
+                "title": {"text": "Orders", "font_size": 18},
+                "format": "png",
+            }),
+            value
+        );
+
+        let err = expand_template(json!({"template": "not_found"})).unwrap_err();
+        assert_eq!(404, err.status);
+        // 未指定模板时原样返回
+        let value = json!({"type": "bar"});
+        assert_eq!(value, expand_template(value.clone()).unwrap());
+    }
+}
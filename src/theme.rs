@@ -0,0 +1,99 @@
+use charts_rs::Theme;
+use config::{Config, File, FileFormat};
+use glob::glob;
+use std::{env, fs};
+use substring::Substring;
+use tracing::{error, info};
+
+use crate::cache::invalidate_render_data;
+use crate::error::{HttpError, HttpResult};
+
+/// 主题名称仅支持字母、数字、-以及_
+fn is_valid_theme_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 以json或yaml解析主题
+pub fn parse_theme(data: &str, format: FileFormat) -> HttpResult<Theme> {
+    let theme = match format {
+        FileFormat::Json => serde_json::from_str::<Theme>(data)
+            .map_err(|err| HttpError::new_with_category(&err.to_string(), "theme"))?,
+        _ => Config::builder()
+            .add_source(File::from_str(data, format))
+            .build()
+            .and_then(|config| config.try_deserialize::<Theme>())
+            .map_err(|err| HttpError::new_with_category(&err.to_string(), "theme"))?,
+    };
+    Ok(theme)
+}
+
+/// 添加或更新主题，已生成的图表缓存失效
+pub fn add_theme(name: &str, theme: Theme) -> HttpResult<()> {
+    if !is_valid_theme_name(name) {
+        return Err(HttpError::new_with_category(
+            &format!("Theme name {name} is invalid"),
+            "theme",
+        ));
+    }
+    charts_rs::add_theme(name, theme);
+    invalidate_render_data();
+    Ok(())
+}
+
+/// 获取主题，不存在则返回None
+pub fn get_theme(name: &str) -> Option<Theme> {
+    if !charts_rs::list_theme_name().iter().any(|item| item == name) {
+        return None;
+    }
+    Some(charts_rs::get_theme(name).as_ref().clone())
+}
+
+/// 从env中加载主题，env名称为CHARTS_THEME_主题名称
+pub fn load_themes_from_env() {
+    let prefix = "CHARTS_THEME_";
+    for (name, value) in env::vars() {
+        if !name.starts_with(prefix) {
+            continue;
+        }
+        let name = name.substring(prefix.len(), name.len());
+        if name.is_empty() {
+            continue;
+        }
+        match parse_theme(&value, FileFormat::Json).and_then(|theme| add_theme(name, theme)) {
+            Ok(()) => info!(name, "add theme success"),
+            Err(err) => error!(name, error = err.message, "add theme fail"),
+        }
+    }
+}
+
+/// 从目录中加载主题(json或yaml)，文件名为主题名称
+pub fn load_themes(dir: &str) {
+    let file_paths = [
+        (format!("{dir}/*.json"), FileFormat::Json),
+        (format!("{dir}/*.yml"), FileFormat::Yaml),
+        (format!("{dir}/*.yaml"), FileFormat::Yaml),
+    ];
+    for (file_path, format) in file_paths {
+        for entry in glob(&file_path)
+            .expect("Failed to read glob pattern")
+            .flatten()
+        {
+            let file = entry.to_string_lossy().to_string();
+            let name = entry
+                .file_stem()
+                .map(|value| value.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let result = fs::read_to_string(&entry)
+                .map_err(|err| HttpError::new_with_category(&err.to_string(), "theme"))
+                .and_then(|data| parse_theme(&data, format))
+                .and_then(|theme| add_theme(&name, theme));
+            match result {
+                Ok(()) => info!(name, file, "load theme success"),
+                Err(err) => error!(name, file, error = err.message, "load theme fail"),
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::config::get_env;
 
 mod http;
+mod svg;
 
 /// 是否开发环境
 /// 用于针对本地开发时的判断
@@ -8,3 +9,4 @@ pub fn is_development() -> bool {
     get_env() == "dev"
 }
 pub use http::*;
+pub use svg::*;
@@ -0,0 +1,66 @@
+/// 获取svg根节点中的属性值
+fn get_root_attr(root: &str, name: &str) -> Option<(usize, usize)> {
+    let key = format!(r#" {name}=""#);
+    let start = root.find(&key)? + key.len();
+    let end = start + root[start..].find('"')?;
+    Some((start, end))
+}
+
+/// 获取svg的宽高(根节点的width与height)
+pub fn get_svg_size(svg: &str) -> Option<(f32, f32)> {
+    let start = svg.find("<svg")?;
+    let end = start + svg[start..].find('>')?;
+    let root = &svg[start..end];
+    let (width_start, width_end) = get_root_attr(root, "width")?;
+    let (height_start, height_end) = get_root_attr(root, "height")?;
+    let width = root[width_start..width_end].parse::<f32>().ok()?;
+    let height = root[height_start..height_end].parse::<f32>().ok()?;
+    Some((width, height))
+}
+
+/// 按比例修改svg的宽高，viewBox不变，因此栅格化时布局一致而分辨率按比例变化
+pub fn scale_svg(svg: &str, scale: f32) -> Option<String> {
+    let (width, height) = get_svg_size(svg)?;
+    let start = svg.find("<svg")?;
+    let end = start + svg[start..].find('>')?;
+    let mut root = svg[start..end].to_string();
+    if !root.contains(" viewBox=") {
+        root.push_str(&format!(r#" viewBox="0 0 {width} {height}""#));
+    }
+    for (name, value) in [("width", width * scale), ("height", height * scale)] {
+        let (value_start, value_end) = get_root_attr(&root, name)?;
+        root.replace_range(value_start..value_end, &value.to_string());
+    }
+    Some(format!("{}{root}{}", &svg[..start], &svg[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_svg_size, scale_svg};
+
+    #[test]
+    fn get_size() {
+        let svg = r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" width="600" height="400.5"><rect width="10" height="10"/></svg>"#;
+        assert_eq!(Some((600.0, 400.5)), get_svg_size(svg));
+        assert_eq!(None, get_svg_size(r#"<svg width="600"></svg>"#));
+        assert_eq!(None, get_svg_size("<rect/>"));
+    }
+
+    #[test]
+    fn scale_size() {
+        let svg = r#"<svg width="600" height="400"><rect width="10" height="10"/></svg>"#;
+        // 仅修改根节点的宽高，并添加viewBox
+        assert_eq!(
+            r#"<svg width="1200" height="800" viewBox="0 0 600 400"><rect width="10" height="10"/></svg>"#,
+            scale_svg(svg, 2.0).unwrap()
+        );
+
+        // 已有viewBox则保持不变
+        let svg = r#"<svg viewBox="0 0 300 200" width="600" height="400"></svg>"#;
+        assert_eq!(
+            r#"<svg viewBox="0 0 300 200" width="300" height="200"></svg>"#,
+            scale_svg(svg, 0.5).unwrap()
+        );
+        assert_eq!(None, scale_svg("<svg></svg>", 2.0));
+    }
+}